@@ -0,0 +1,31 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS domains (
+    id UUID PRIMARY KEY DEFAULT (uuid_generate_v4()),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    hostname VARCHAR(255) NOT NULL,
+    verification_token VARCHAR(255) NOT NULL,
+    verified_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ DEFAULT now(),
+    updated_at TIMESTAMPTZ DEFAULT now(),
+    UNIQUE (user_id, hostname)
+);
+
+-- Anyone may claim a hostname, but only one verified owner can serve it.
+CREATE UNIQUE INDEX IF NOT EXISTS domains_verified_hostname_key
+    ON domains (hostname) WHERE verified_at IS NOT NULL;
+
+ALTER TABLE urls ADD COLUMN IF NOT EXISTS domain_id UUID REFERENCES domains(id) ON DELETE CASCADE;
+
+-- Short codes used to be unique across the whole table, they are now unique per domain,
+-- with links on the shared domain (domain_id IS NULL) forming their own namespace.
+ALTER TABLE urls DROP CONSTRAINT IF EXISTS urls_short_url_key;
+ALTER TABLE urls DROP CONSTRAINT IF EXISTS urls_slug_key;
+
+CREATE UNIQUE INDEX IF NOT EXISTS urls_shared_short_url_key
+    ON urls (short_url) WHERE domain_id IS NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS urls_domain_short_url_key
+    ON urls (domain_id, short_url) WHERE domain_id IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS urls_shared_slug_key
+    ON urls (slug) WHERE domain_id IS NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS urls_domain_slug_key
+    ON urls (domain_id, slug) WHERE domain_id IS NOT NULL;
@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE urls ADD COLUMN IF NOT EXISTS flagged_at TIMESTAMPTZ;
+ALTER TABLE urls ADD COLUMN IF NOT EXISTS flag_reason TEXT;
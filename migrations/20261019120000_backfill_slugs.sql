@@ -0,0 +1,19 @@
+-- Add migration script here
+-- Slugs fold case, `_` and repeated separators, so two free short codes such as `foo_bar`
+-- and `foo-bar` share a slug. Only the short code has to be unique, the slug is for display.
+DROP INDEX IF EXISTS urls_shared_slug_key;
+DROP INDEX IF EXISTS urls_domain_slug_key;
+
+-- Recomputes every slug the way utils::slugify now does. Short codes are limited to
+-- ASCII letters, digits, underscores and spaces, so no transliteration is needed here.
+WITH computed AS (
+    SELECT
+        id,
+        trim(BOTH '-' FROM regexp_replace(lower(short_url), '[^a-z0-9]+', '-', 'g')) AS new_slug
+    FROM urls
+)
+UPDATE urls
+SET slug = computed.new_slug
+FROM computed
+WHERE urls.id = computed.id
+AND urls.slug IS DISTINCT FROM computed.new_slug;
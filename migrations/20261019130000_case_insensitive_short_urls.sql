@@ -0,0 +1,68 @@
+-- Add migration script here
+ALTER TABLE domains ADD COLUMN IF NOT EXISTS case_insensitive BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Copy of the domain's setting, an index cannot look into another table. Kept in sync by
+-- the triggers below, shared links (no domain) stay FALSE, the global setting has its own
+-- index created at startup.
+ALTER TABLE urls ADD COLUMN IF NOT EXISTS case_insensitive BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE OR REPLACE FUNCTION urls_copy_domain_case_insensitive() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.case_insensitive := COALESCE(
+        (SELECT case_insensitive FROM domains WHERE id = NEW.domain_id),
+        FALSE
+    );
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS urls_copy_domain_case_insensitive ON urls;
+CREATE TRIGGER urls_copy_domain_case_insensitive
+    BEFORE INSERT OR UPDATE OF domain_id ON urls
+    FOR EACH ROW EXECUTE FUNCTION urls_copy_domain_case_insensitive();
+
+CREATE OR REPLACE FUNCTION domains_share_case_insensitive() RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE urls SET case_insensitive = NEW.case_insensitive WHERE domain_id = NEW.id;
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS domains_share_case_insensitive ON domains;
+CREATE TRIGGER domains_share_case_insensitive
+    AFTER UPDATE OF case_insensitive ON domains
+    FOR EACH ROW WHEN (OLD.case_insensitive IS DISTINCT FROM NEW.case_insensitive)
+    EXECUTE FUNCTION domains_share_case_insensitive();
+
+-- Short codes that only differ by case within the same domain. They make case-insensitive
+-- lookups ambiguous and keep the setting from being turned on until one of them is renamed.
+CREATE OR REPLACE VIEW short_url_case_collisions AS
+SELECT
+    domain_id,
+    lower(short_url) AS normalized_short_url,
+    array_agg(id ORDER BY created_at, id) AS url_ids,
+    array_agg(short_url ORDER BY created_at, id) AS short_urls
+FROM urls
+GROUP BY domain_id, lower(short_url)
+HAVING count(*) > 1;
+
+DO $$
+DECLARE
+    collisions INTEGER;
+BEGIN
+    SELECT count(*) INTO collisions FROM short_url_case_collisions;
+    IF collisions > 0 THEN
+        RAISE WARNING '% short codes collide when compared case-insensitively, see the short_url_case_collisions view', collisions;
+    END IF;
+END $$;
+
+-- Case-insensitive lookups.
+CREATE INDEX IF NOT EXISTS urls_shared_short_url_lower_idx
+    ON urls (lower(short_url)) WHERE domain_id IS NULL;
+CREATE INDEX IF NOT EXISTS urls_domain_short_url_lower_idx
+    ON urls (domain_id, lower(short_url)) WHERE domain_id IS NOT NULL;
+
+-- Uniqueness ignoring case on the domains that ask for it. No domain does yet, so this
+-- cannot fail, turning the setting on fails instead while codes collide.
+CREATE UNIQUE INDEX IF NOT EXISTS urls_domain_short_url_lower_key
+    ON urls (domain_id, lower(short_url)) WHERE case_insensitive;
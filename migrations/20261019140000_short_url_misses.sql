@@ -0,0 +1,13 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS short_url_misses (
+    id UUID PRIMARY KEY DEFAULT (uuid_generate_v4()),
+    domain_id UUID REFERENCES domains(id) ON DELETE CASCADE,
+    short_url VARCHAR(255) NOT NULL,
+    hits BIGINT NOT NULL DEFAULT 1,
+    first_seen_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- One row per code and namespace, the shared namespace has no domain.
+CREATE UNIQUE INDEX IF NOT EXISTS short_url_misses_namespace_key
+    ON short_url_misses ((COALESCE(domain_id, '00000000-0000-0000-0000-000000000000'::UUID)), short_url);
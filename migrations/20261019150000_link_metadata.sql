@@ -0,0 +1,6 @@
+-- Add migration script here
+ALTER TABLE urls ADD COLUMN IF NOT EXISTS meta_title TEXT;
+ALTER TABLE urls ADD COLUMN IF NOT EXISTS meta_description TEXT;
+ALTER TABLE urls ADD COLUMN IF NOT EXISTS meta_image_url TEXT;
+ALTER TABLE urls ADD COLUMN IF NOT EXISTS meta_favicon_url TEXT;
+ALTER TABLE urls ADD COLUMN IF NOT EXISTS metadata_fetched_at TIMESTAMPTZ;
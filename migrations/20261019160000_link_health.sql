@@ -0,0 +1,6 @@
+-- Add migration script here
+ALTER TABLE urls ADD COLUMN IF NOT EXISTS health_status_code INTEGER;
+ALTER TABLE urls ADD COLUMN IF NOT EXISTS health_latency_ms INTEGER;
+ALTER TABLE urls ADD COLUMN IF NOT EXISTS health_error TEXT;
+ALTER TABLE urls ADD COLUMN IF NOT EXISTS health_checked_at TIMESTAMPTZ;
+ALTER TABLE urls ADD COLUMN IF NOT EXISTS health_failures INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,6 @@
+-- Add migration script here
+ALTER TABLE urls ADD COLUMN IF NOT EXISTS title TEXT;
+ALTER TABLE urls ADD COLUMN IF NOT EXISTS notes TEXT;
+ALTER TABLE urls ADD COLUMN IF NOT EXISTS is_favorite BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX IF NOT EXISTS urls_user_favorite_idx ON urls (user_id) WHERE is_favorite;
@@ -0,0 +1,21 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS url_revisions (
+    id UUID PRIMARY KEY DEFAULT (uuid_generate_v4()),
+    url_id UUID NOT NULL REFERENCES urls(id) ON DELETE CASCADE,
+    changed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    original_url TEXT NOT NULL,
+    short_url VARCHAR(255) NOT NULL,
+    category VARCHAR(255) NOT NULL,
+    title TEXT,
+    notes TEXT,
+    is_favorite BOOLEAN NOT NULL,
+    collection_id UUID REFERENCES collections(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS url_revisions_url_id_idx ON url_revisions (url_id, created_at DESC);
+
+-- Existing links start their history with their current state.
+INSERT INTO url_revisions (url_id, changed_by, original_url, short_url, category, title, notes, is_favorite, collection_id, created_at)
+SELECT id, user_id, original_url, short_url, category, title, notes, is_favorite, collection_id, COALESCE(updated_at, created_at, now())
+FROM urls;
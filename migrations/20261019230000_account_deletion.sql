@@ -0,0 +1,3 @@
+-- Add migration script here
+-- Set when the user asks to delete the account, the account goes once the grace period is over.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS deletion_requested_at TIMESTAMPTZ;
@@ -0,0 +1,41 @@
+//! Accounts are deleted once the grace period after the request is over. Their links,
+//! collections and keys go with them through `ON DELETE CASCADE`.
+
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tracing::{info, warn};
+
+use crate::custom_error::CustomError;
+
+/// Deletes the accounts whose grace period is over, returns how many there were.
+pub async fn purge_deleted_accounts(db: &PgPool, grace_days: i32) -> Result<u64, CustomError> {
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM users
+        WHERE deletion_requested_at IS NOT NULL
+          AND deletion_requested_at < now() - make_interval(days => $1)
+        "#,
+        grace_days
+    )
+    .execute(db)
+    .await
+    .map_err(CustomError::DataBaseError)?
+    .rows_affected();
+
+    Ok(deleted)
+}
+
+/// Runs `purge_deleted_accounts` every `every`.
+pub async fn watch_account_deletions(db: PgPool, grace_days: i32, every: Duration) {
+    let mut interval = actix_web::rt::time::interval(every);
+    loop {
+        interval.tick().await;
+
+        match purge_deleted_accounts(&db, grace_days).await {
+            Ok(0) => {}
+            Ok(count) => info!("Deleted {} accounts after their grace period", count),
+            Err(err) => warn!("Failed to delete accounts: {}", err),
+        }
+    }
+}
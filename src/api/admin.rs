@@ -0,0 +1,111 @@
+use actix_web::{get, web, HttpResponse};
+
+use crate::app_state::AppState;
+
+use crate::jwt_auth::JwtMiddleware;
+
+use crate::custom_error::{CustomError, CustomHttpError};
+
+use crate::redirect_chain::find_redirect_loops;
+
+use crate::scope::Scope;
+
+#[get("/admin/url/loops")]
+pub async fn get_redirect_loops(
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::AccountAdmin)?;
+
+    if !auth_guard.user.is_admin {
+        return Err(CustomError::HttpError(CustomHttpError::AdminOnly));
+    }
+
+    let loops = find_redirect_loops(&data.db, &data.secrets).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "results": loops.len(),
+        "data": loops
+    })))
+}
+
+#[get("/admin/url/case-collisions")]
+pub async fn get_short_url_case_collisions(
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::AccountAdmin)?;
+
+    if !auth_guard.user.is_admin {
+        return Err(CustomError::HttpError(CustomHttpError::AdminOnly));
+    }
+
+    let collisions = sqlx::query!(
+        r#"
+        SELECT domain_id, normalized_short_url AS "normalized_short_url!",
+               url_ids AS "url_ids!", short_urls AS "short_urls!"
+        FROM short_url_case_collisions
+        ORDER BY normalized_short_url
+        "#
+    )
+    .fetch_all(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?
+    .into_iter()
+    .map(|collision| {
+        serde_json::json!({
+            "domain_id": collision.domain_id,
+            "normalized_short_url": collision.normalized_short_url,
+            "url_ids": collision.url_ids,
+            "short_urls": collision.short_urls,
+        })
+    })
+    .collect::<Vec<_>>();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "results": collisions.len(),
+        "data": collisions
+    })))
+}
+
+#[get("/admin/url/misses")]
+pub async fn get_short_url_misses(
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::AccountAdmin)?;
+
+    if !auth_guard.user.is_admin {
+        return Err(CustomError::HttpError(CustomHttpError::AdminOnly));
+    }
+
+    let misses = sqlx::query!(
+        r#"
+        SELECT domain_id, short_url, hits, first_seen_at, last_seen_at
+        FROM short_url_misses
+        ORDER BY hits DESC, last_seen_at DESC
+        "#
+    )
+    .fetch_all(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?
+    .into_iter()
+    .map(|miss| {
+        serde_json::json!({
+            "domain_id": miss.domain_id,
+            "short_url": miss.short_url,
+            "hits": miss.hits,
+            "first_seen_at": miss.first_seen_at,
+            "last_seen_at": miss.last_seen_at,
+        })
+    })
+    .collect::<Vec<_>>();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "results": misses.len(),
+        "data": misses
+    })))
+}
@@ -0,0 +1,129 @@
+use actix_web::{delete, get, post, web, HttpResponse};
+
+use validator::Validate;
+
+use crate::models::api_key::{ApiKey, ApiKeyPath, CreateApiKey};
+
+use crate::app_state::AppState;
+
+use crate::jwt_auth::JwtMiddleware;
+
+use crate::custom_error::{handle_validation_error, CustomError, CustomHttpError};
+
+use crate::api_key_auth::{display_prefix, generate_api_key, hash_api_key};
+
+use crate::scope::Scope;
+
+#[post("/api-keys")]
+pub async fn create_api_key(
+    body: web::Json<CreateApiKey>,
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::AccountAdmin)?;
+    auth_guard.require_verified_email()?;
+
+    if let Err(validation_error) = body.validate() {
+        return handle_validation_error(validation_error);
+    }
+
+    if body
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= chrono::Utc::now())
+    {
+        return Err(CustomError::HttpError(CustomHttpError::ApiKeyExpiryInPast));
+    }
+
+    let scopes = body
+        .scopes
+        .clone()
+        .unwrap_or_else(|| vec![Scope::LinksRead, Scope::LinksWrite]);
+
+    // A key never grants more than the credential that created it.
+    for scope in &scopes {
+        auth_guard.require(*scope)?;
+    }
+
+    let scopes: Vec<String> = scopes.iter().map(Scope::to_string).collect();
+
+    let key = generate_api_key();
+
+    let api_key = sqlx::query_as!(
+        ApiKey,
+        r#"
+        INSERT INTO api_keys (user_id, name, prefix, key_hash, scopes, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+        auth_guard.user.id,
+        body.name.trim(),
+        display_prefix(&key),
+        hash_api_key(&key),
+        &scopes,
+        body.expires_at
+    )
+    .fetch_one(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    // The only time the key is ever shown, only its hash is kept.
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "status": "success",
+        "data": {
+            "api_key": api_key,
+            "key": key
+        }
+    })))
+}
+
+#[get("/api-keys")]
+pub async fn get_api_keys(
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::AccountAdmin)?;
+
+    let api_keys = sqlx::query_as!(
+        ApiKey,
+        r#"SELECT * FROM api_keys WHERE user_id = $1 ORDER BY created_at DESC"#,
+        auth_guard.user.id
+    )
+    .fetch_all(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "results": api_keys.len(),
+        "data": api_keys
+    })))
+}
+
+#[delete("/api-keys/{api_key_id}")]
+pub async fn revoke_api_key(
+    path: web::Path<ApiKeyPath>,
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::AccountAdmin)?;
+
+    let api_key = sqlx::query_as!(
+        ApiKey,
+        r#"
+        UPDATE api_keys SET revoked_at = COALESCE(revoked_at, now())
+        WHERE id = $1 AND user_id = $2
+        RETURNING *
+        "#,
+        path.api_key_id,
+        auth_guard.user.id
+    )
+    .fetch_optional(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?
+    .ok_or(CustomError::HttpError(CustomHttpError::ApiKeyNotFound))?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "data": api_key
+    })))
+}
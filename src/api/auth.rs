@@ -1,19 +1,30 @@
 use actix_web::HttpRequest;
 use actix_web::{
     cookie::{time::Duration as ActixWebDuration, Cookie},
-    get, post, web, HttpResponse, Responder,
+    delete, get, post, web, HttpResponse, HttpResponseBuilder,
 };
-use serde_json::json;
 use uuid::Uuid;
 
 use validator::Validate;
 
-use crate::models::user::{LoginUserSchema, RegisterUserSchema, User};
+use crate::models::session::SessionPath;
+
+use crate::models::user::{LoginUserSchema, RegisterUserSchema, User, VerifyEmailQuery};
+
+use crate::mailer::Email;
 
 use super::reponse::{filter_user_record, UserResponse};
 
 use crate::app_state::AppState;
 
+use crate::token::action_token::{generate_action_token, verify_action_token, TokenPurpose};
+
+use crate::token::family::{add_to_family, family_of, revoke_family};
+
+use crate::token::session::{
+    end_all_sessions, end_session, list_sessions, owns_session, record_session, ClientInfo,
+};
+
 use crate::token::token::{generate_jwt_token, verify_jwt_token};
 
 use argon2::{
@@ -23,12 +34,54 @@ use argon2::{
 
 use redis::AsyncCommands;
 
+use tracing::{error, info, warn};
+
+use lazy_static::lazy_static;
+
+use crate::login_throttle::{locked_out_for, record_failure, record_success, ThrottleKey};
+
 use crate::jwt_auth::JwtMiddleware;
 
+use crate::scope::Scope;
+
 use crate::custom_error::{handle_validation_error, CustomError, CustomHttpError};
 
+fn refresh_cookie(data: &AppState, refresh_token: String) -> Cookie<'static> {
+    Cookie::build("refresh_token", refresh_token)
+        .path("/")
+        .max_age(ActixWebDuration::seconds(
+            data.secrets.refresh_token_max_age * 60,
+        ))
+        .http_only(true)
+        .domain(data.secrets.domain.clone())
+        .secure(cfg!(not(debug_assertions)))
+        .same_site(actix_web::cookie::SameSite::None)
+        .finish()
+}
+
+/// Deletes tokens that were stored but could not be tracked in their family or session,
+/// so that no token is usable without being revocable.
+async fn discard_tokens(redis_client: &mut redis::aio::Connection, token_uuids: &[Uuid]) {
+    let keys: Vec<String> = token_uuids.iter().map(Uuid::to_string).collect();
+    if let Err(err) = redis_client.del::<_, ()>(keys).await {
+        error!("Failed to discard untracked tokens: {:?}", err);
+    }
+}
+
+/// Minutes the user has to enter the second factor after the password.
+const MFA_CHALLENGE_MAX_AGE: i64 = 5;
+
+lazy_static! {
+    /// Checked against when the email is unknown, so that both failures take as long.
+    static ref DUMMY_PASSWORD_HASH: String = Argon2::default()
+        .hash_password(b"not-a-real-password", &SaltString::generate(&mut OsRng))
+        .expect("Error hashing password")
+        .to_string();
+}
+
 #[post("/auth/login")]
 pub async fn login(
+    req: HttpRequest,
     body: web::Json<LoginUserSchema>,
     data: web::Data<AppState>,
 ) -> Result<HttpResponse, CustomError> {
@@ -38,44 +91,112 @@ pub async fn login(
         return handle_validation_error(validation_error);
     }
 
-    let user = match sqlx::query_as!(
-        User,
-        r#"SELECT * FROM "users" WHERE email = $1"#,
-        body.email.to_string()
-    )
-    .fetch_optional(&data.db)
-    .await
+    let email = body.email.to_lowercase();
+    let ip = ClientInfo::from_request(&req, &data.secrets)
+        .ip
+        .unwrap_or_default();
+    let throttle_keys = [ThrottleKey::Ip(&ip), ThrottleKey::Account(&email)];
+
+    let mut redis_client = data
+        .redis_client
+        .get_async_connection()
+        .await
+        .map_err(CustomError::RedisError)?;
+
+    if let Some(retry_after) = locked_out_for(&mut redis_client, &throttle_keys)
+        .await
+        .map_err(CustomError::RedisError)?
     {
-        Ok(user) => {
-            if let Some(user) = user {
-                user
-            } else {
-                print!("user not found");
-                return Err(CustomError::HttpError(CustomHttpError::UserNotFound));
-            }
-        }
-        Err(e) => {
-            print!("there is not a client with that");
-            return Err(CustomError::DataBaseError(e));
-        }
-    };
+        info!(target: "security", %email, %ip, outcome = "locked_out", "Login attempt");
+        return Err(CustomError::HttpError(
+            CustomHttpError::TooManyLoginAttempts(retry_after),
+        ));
+    }
+
+    let user = sqlx::query_as!(User, r#"SELECT * FROM "users" WHERE email = $1"#, email)
+        .fetch_optional(&data.db)
+        .await
+        .map_err(CustomError::DataBaseError)?;
 
-    let is_valid = PasswordHash::new(&user.password)
+    // An unknown email costs the same hash check and gets the same error as a wrong password.
+    let password_hash = user
+        .as_ref()
+        .map_or(DUMMY_PASSWORD_HASH.as_str(), |user| user.password.as_str());
+    let is_valid = PasswordHash::new(password_hash)
         .and_then(|parsed_hash| {
             Argon2::default().verify_password(body.password.as_bytes(), &parsed_hash)
         })
-        .map_or(false, |_| true);
+        .is_ok();
+
+    let user = match user {
+        Some(user) if is_valid => user,
+        _ => {
+            info!(target: "security", %email, %ip, outcome = "failure", "Login attempt");
+            record_failure(&mut redis_client, &data.secrets, &throttle_keys)
+                .await
+                .map_err(CustomError::RedisError)?;
+            return Err(CustomError::HttpError(
+                CustomHttpError::CredentialsNotCorrect,
+            ));
+        }
+    };
 
-    if !is_valid {
-        return Err(CustomError::HttpError(
-            CustomHttpError::CredentialsNotCorrect,
-        ));
+    info!(target: "security", %email, %ip, outcome = "success", "Login attempt");
+    record_success(&mut redis_client, &email)
+        .await
+        .map_err(CustomError::RedisError)?;
+
+    complete_login(&req, &data, &user).await
+}
+
+/// Ends a login whose first factor passed, asking for the second one when 2FA is enabled.
+pub(crate) async fn complete_login(
+    req: &HttpRequest,
+    data: &AppState,
+    user: &User,
+) -> Result<HttpResponse, CustomError> {
+    // The tokens are only handed out once the second factor is checked as well.
+    if user.totp_enabled_at.is_some() {
+        let mfa_token = generate_action_token(
+            user.id,
+            &user.email,
+            TokenPurpose::MfaChallenge,
+            MFA_CHALLENGE_MAX_AGE,
+            &data.secrets.access_token_private_key,
+        )
+        .map_err(|err| CustomError::OtherError(err.to_string()))?;
+
+        return Ok(HttpResponse::Ok().json(serde_json::json!({
+            "status": "mfa_required",
+            "mfa_token": mfa_token
+        })));
+    }
+
+    issue_tokens(req, data, user).await
+}
+
+/// Starts a session for a user who passed every login check.
+pub(crate) async fn issue_tokens(
+    req: &HttpRequest,
+    data: &AppState,
+    user: &User,
+) -> Result<HttpResponse, CustomError> {
+    // Logging in during the grace period keeps the account.
+    if user.deletion_requested_at.is_some() {
+        sqlx::query!(
+            r#"UPDATE users SET deletion_requested_at = NULL, updated_at = now() WHERE id = $1"#,
+            user.id
+        )
+        .execute(&data.db)
+        .await
+        .map_err(CustomError::DataBaseError)?;
     }
 
     let access_token_details = generate_jwt_token(
         user.id,
         data.secrets.access_token_max_age,
         data.secrets.access_token_private_key.to_owned(),
+        Scope::ALL.to_vec(),
     )
     .map_err(|err| CustomError::OtherError(err.to_string()))?;
 
@@ -83,6 +204,7 @@ pub async fn login(
         user.id,
         data.secrets.refresh_token_max_age,
         data.secrets.refresh_token_private_key.to_owned(),
+        Scope::ALL.to_vec(),
     )
     .map_err(|err| CustomError::OtherError(err.to_string()))?;
 
@@ -90,7 +212,7 @@ pub async fn login(
         .redis_client
         .get_async_connection()
         .await
-        .map_err(|err| CustomError::RedisError(err))?;
+        .map_err(CustomError::RedisError)?;
 
     let access_result: redis::RedisResult<()> = redis_client
         .set_ex(
@@ -118,22 +240,49 @@ pub async fn login(
         return Err(CustomError::HttpError(CustomHttpError::RedisProblem));
     }
 
-    let refresh_cookie = Cookie::build(
-        "refresh_token",
-        refresh_token_details.token.clone().unwrap(),
+    let family_result = add_to_family(
+        &mut redis_client,
+        refresh_token_details.token_uuid,
+        refresh_token_details.token_uuid,
+        access_token_details.token_uuid,
+        (data.secrets.refresh_token_max_age * 60) as usize,
+    )
+    .await;
+
+    if let Err(e) = family_result {
+        error!("Failed to record the refresh token family: {:?}", e);
+        discard_tokens(
+            &mut redis_client,
+            &[
+                access_token_details.token_uuid,
+                refresh_token_details.token_uuid,
+            ],
+        )
+        .await;
+        return Err(CustomError::HttpError(CustomHttpError::RedisProblem));
+    }
+
+    // The session shares its id with the token family started here.
+    let session_result = record_session(
+        &mut redis_client,
+        refresh_token_details.token_uuid,
+        user.id,
+        &ClientInfo::from_request(req, &data.secrets),
+        (data.secrets.refresh_token_max_age * 60) as usize,
     )
-    .path("/")
-    .max_age(ActixWebDuration::seconds(
-        data.secrets.refresh_token_max_age * 60,
-    ))
-    .http_only(true)
-    .domain(data.secrets.domain.clone())
-    .secure(cfg!(not(debug_assertions)))
-    .same_site(actix_web::cookie::SameSite::None)
-    .finish();
+    .await;
+
+    // Without its session the family could not be listed or ended by the user.
+    if let Err(e) = session_result {
+        error!("Failed to record the session: {:?}", e);
+        if let Err(err) = revoke_family(&mut redis_client, refresh_token_details.token_uuid).await {
+            error!("Failed to revoke the token family: {:?}", err);
+        }
+        return Err(CustomError::HttpError(CustomHttpError::RedisProblem));
+    }
 
     Ok(HttpResponse::Ok()
-        .cookie(refresh_cookie)
+        .cookie(refresh_cookie(data, refresh_token_details.token.clone().unwrap()))
         .json(serde_json::json!({"status": "success", "access_token": access_token_details.token.unwrap()})))
 }
 
@@ -183,14 +332,101 @@ pub async fn register(
     .fetch_one(&data.db)
     .await
     {
-        Ok(user) => Ok(HttpResponse::Ok().json(UserResponse {
-            status: "success".to_string(),
-            data: filter_user_record(&user),
-        })),
-        Err(e) => {
-            return Err(CustomError::DataBaseError(e));
+        Ok(user) => {
+            // The account exists either way, the user can ask for another email.
+            if let Err(err) = send_verification_email(&data, &user).await {
+                warn!("Failed to send the verification email: {}", err);
+            }
+
+            Ok(HttpResponse::Ok().json(UserResponse {
+                status: "success".to_string(),
+                data: filter_user_record(&user),
+            }))
         }
+        Err(e) => Err(CustomError::DataBaseError(e)),
+    }
+}
+
+pub(crate) async fn send_verification_email(
+    data: &AppState,
+    user: &User,
+) -> Result<(), CustomError> {
+    let token = generate_action_token(
+        user.id,
+        &user.email,
+        TokenPurpose::VerifyEmail,
+        data.secrets.email_verification_token_max_age,
+        &data.secrets.access_token_private_key,
+    )
+    .map_err(|err| CustomError::OtherError(err.to_string()))?;
+
+    let email = Email {
+        to: user.email.clone(),
+        subject: "Verify your email address".to_string(),
+        body: format!(
+            "Hi {},\n\nPlease confirm your email address by opening this link:\n\n{}/api/auth/verify?token={}\n\nThe link expires in {} minutes.\n",
+            user.name, data.secrets.public_url, token, data.secrets.email_verification_token_max_age
+        ),
+    };
+
+    data.mailer.send(&email).await
+}
+
+#[get("/auth/verify")]
+async fn verify_email(
+    query: web::Query<VerifyEmailQuery>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let claims = verify_action_token(
+        &data.secrets.access_token_public_key,
+        &query.token,
+        TokenPurpose::VerifyEmail,
+    )
+    .map_err(|_| CustomError::HttpError(CustomHttpError::VerificationTokenInvalid))?;
+
+    let user = sqlx::query_as!(
+        User,
+        r#"
+        UPDATE users
+        SET email_verified_at = COALESCE(email_verified_at, now()), updated_at = now()
+        WHERE id = $1 AND email = $2
+        RETURNING *
+        "#,
+        claims.sub,
+        claims.email
+    )
+    .fetch_optional(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?
+    .ok_or(CustomError::HttpError(
+        CustomHttpError::VerificationTokenInvalid,
+    ))?;
+
+    Ok(HttpResponse::Ok().json(UserResponse {
+        status: "success".to_string(),
+        data: filter_user_record(&user),
+    }))
+}
+
+#[post("/auth/verify/resend")]
+async fn resend_verification_email(
+    auth_guard: JwtMiddleware,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::AccountAdmin)?;
+
+    if auth_guard.user.email_verified_at.is_some() {
+        return Err(CustomError::HttpError(
+            CustomHttpError::EmailAlreadyVerified,
+        ));
     }
+
+    send_verification_email(&data, &auth_guard.user).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "message": "Verification email sent"
+    })))
 }
 
 #[get("/auth/refresh")]
@@ -220,13 +456,36 @@ async fn refresh_access_token(
             return Err(CustomError::RedisError(e));
         }
     };
-    let redis_result: redis::RedisResult<String> = redis_client
+    let family_id = family_of(&mut redis_client, refresh_token_details.token_uuid)
+        .await
+        .map_err(CustomError::RedisError)?;
+
+    let redis_result: redis::RedisResult<Option<String>> = redis_client
         .get(refresh_token_details.token_uuid.to_string())
         .await;
 
-    let user_id = match redis_result {
-        Ok(value) => value,
-        Err(_) => {
+    // Deleting the token retires it, and tells us whether a concurrent refresh got there first.
+    let retired: usize = redis_client
+        .del(refresh_token_details.token_uuid.to_string())
+        .await
+        .map_err(CustomError::RedisError)?;
+
+    let user_id = match (redis_result, retired, family_id) {
+        (Ok(Some(value)), 1, _) => value,
+        (_, _, Some(family_id)) => {
+            warn!(
+                target: "security",
+                user_id = %refresh_token_details.user_id,
+                family_id = %family_id,
+                token_uuid = %refresh_token_details.token_uuid,
+                "Retired refresh token reused, revoking its token family"
+            );
+            end_session(&mut redis_client, refresh_token_details.user_id, family_id)
+                .await
+                .map_err(CustomError::RedisError)?;
+            return Err(CustomError::HttpError(CustomHttpError::RefreshTokenReused));
+        }
+        _ => {
             return Err(CustomError::HttpError(CustomHttpError::UserNotInRedis));
         }
     };
@@ -247,25 +506,92 @@ async fn refresh_access_token(
         user.id,
         data.secrets.access_token_max_age,
         data.secrets.access_token_private_key.to_owned(),
+        refresh_token_details.scopes.clone(),
+    ) {
+        Ok(token_details) => token_details,
+        Err(_) => return Err(CustomError::HttpError(CustomHttpError::TokenNotGenerated)),
+    };
+
+    let new_refresh_token_details = match generate_jwt_token(
+        user.id,
+        data.secrets.refresh_token_max_age,
+        data.secrets.refresh_token_private_key.to_owned(),
+        refresh_token_details.scopes,
     ) {
         Ok(token_details) => token_details,
         Err(_) => return Err(CustomError::HttpError(CustomHttpError::TokenNotGenerated)),
     };
 
-    let redis_result: redis::RedisResult<()> = redis_client
+    let redis_result: redis::RedisResult<()> = redis::pipe()
         .set_ex(
             access_token_details.token_uuid.to_string(),
             user.id.to_string(),
             (data.secrets.access_token_max_age * 60) as usize,
         )
+        .ignore()
+        .set_ex(
+            new_refresh_token_details.token_uuid.to_string(),
+            user.id.to_string(),
+            (data.secrets.refresh_token_max_age * 60) as usize,
+        )
+        .ignore()
+        .query_async(&mut redis_client)
         .await;
 
     if redis_result.is_err() {
         return Err(CustomError::HttpError(CustomHttpError::RedisProblem));
     }
 
+    // Tokens issued before families existed start their own.
+    let family_id = family_id.unwrap_or(refresh_token_details.token_uuid);
+
+    let family_result = add_to_family(
+        &mut redis_client,
+        family_id,
+        new_refresh_token_details.token_uuid,
+        access_token_details.token_uuid,
+        (data.secrets.refresh_token_max_age * 60) as usize,
+    )
+    .await;
+
+    if let Err(e) = family_result {
+        error!("Failed to record the refresh token family: {:?}", e);
+        discard_tokens(
+            &mut redis_client,
+            &[
+                access_token_details.token_uuid,
+                new_refresh_token_details.token_uuid,
+            ],
+        )
+        .await;
+        return Err(CustomError::HttpError(CustomHttpError::RedisProblem));
+    }
+
+    let session_result = record_session(
+        &mut redis_client,
+        family_id,
+        user.id,
+        &ClientInfo::from_request(&req, &data.secrets),
+        (data.secrets.refresh_token_max_age * 60) as usize,
+    )
+    .await;
+
+    if let Err(e) = session_result {
+        error!("Failed to record the session: {:?}", e);
+        discard_tokens(
+            &mut redis_client,
+            &[
+                access_token_details.token_uuid,
+                new_refresh_token_details.token_uuid,
+            ],
+        )
+        .await;
+        return Err(CustomError::HttpError(CustomHttpError::RedisProblem));
+    }
+
     Ok(HttpResponse::Ok()
-    .json(serde_json::json!({"status": "success", "access_token": access_token_details.token.unwrap()})))
+        .cookie(refresh_cookie(&data, new_refresh_token_details.token.unwrap()))
+        .json(serde_json::json!({"status": "success", "access_token": access_token_details.token.unwrap()})))
 }
 
 #[get("/auth/logout")]
@@ -274,6 +600,8 @@ async fn logout(
     auth_guard: JwtMiddleware,
     data: web::Data<AppState>,
 ) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::AccountAdmin)?;
+
     let refresh_token = match req.cookie("refresh_token") {
         Some(c) => c.value().to_string(),
         None => return Err(CustomError::HttpError(CustomHttpError::TokenNotProvided)),
@@ -287,49 +615,150 @@ async fn logout(
         Err(_) => return Err(CustomError::HttpError(CustomHttpError::TokenNotMatch)),
     };
 
+    let mut token_uuids = vec![refresh_token_details.token_uuid.to_string()];
+    token_uuids.extend(auth_guard.access_token_uuid.map(|uuid| uuid.to_string()));
+
     let mut redis_client = data.redis_client.get_async_connection().await.unwrap();
-    let redis_result: redis::RedisResult<usize> = redis_client
-        .del(&[
-            refresh_token_details.token_uuid.to_string(),
-            auth_guard.access_token_uuid.to_string(),
-        ])
-        .await;
+
+    if let Some(family_id) = family_of(&mut redis_client, refresh_token_details.token_uuid)
+        .await
+        .map_err(CustomError::RedisError)?
+    {
+        end_session(&mut redis_client, auth_guard.user.id, family_id)
+            .await
+            .map_err(CustomError::RedisError)?;
+    }
+
+    let redis_result: redis::RedisResult<usize> = redis_client.del(&token_uuids).await;
 
     match redis_result {
-        Ok(_) => {
-            let access_cookie = Cookie::build("access_token", "")
-                .path("/")
-                .max_age(ActixWebDuration::new(-1, 0))
-                .http_only(true)
-                .finish();
-            let refresh_cookie = Cookie::build("refresh_token", "")
-                .path("/")
-                .max_age(ActixWebDuration::new(-1, 0))
-                .http_only(true)
-                .finish();
-            let logged_in_cookie = Cookie::build("logged_in", "")
-                .path("/")
-                .max_age(ActixWebDuration::new(-1, 0))
-                .http_only(true)
-                .finish();
-
-            Ok(HttpResponse::Ok()
-                .cookie(access_cookie)
-                .cookie(refresh_cookie)
-                .cookie(logged_in_cookie)
-                .json(serde_json::json!({"status": "success"})))
-        }
+        Ok(_) => Ok(logged_out_response().json(serde_json::json!({"status": "success"}))),
         Err(err) => Err(CustomError::RedisError(err)),
     }
 }
 
+/// Expires the auth cookies on the client.
+fn logged_out_response() -> HttpResponseBuilder {
+    let access_cookie = Cookie::build("access_token", "")
+        .path("/")
+        .max_age(ActixWebDuration::new(-1, 0))
+        .http_only(true)
+        .finish();
+    let refresh_cookie = Cookie::build("refresh_token", "")
+        .path("/")
+        .max_age(ActixWebDuration::new(-1, 0))
+        .http_only(true)
+        .finish();
+    let logged_in_cookie = Cookie::build("logged_in", "")
+        .path("/")
+        .max_age(ActixWebDuration::new(-1, 0))
+        .http_only(true)
+        .finish();
+
+    let mut response = HttpResponse::Ok();
+    response
+        .cookie(access_cookie)
+        .cookie(refresh_cookie)
+        .cookie(logged_in_cookie);
+    response
+}
+
+#[get("/auth/sessions")]
+async fn get_sessions(
+    auth_guard: JwtMiddleware,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::AccountAdmin)?;
+
+    let mut redis_client = data
+        .redis_client
+        .get_async_connection()
+        .await
+        .map_err(CustomError::RedisError)?;
+
+    let sessions = list_sessions(&mut redis_client, auth_guard.user.id)
+        .await
+        .map_err(CustomError::RedisError)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "results": sessions.len(),
+        "data": sessions
+    })))
+}
+
+#[delete("/auth/sessions/{session_id}")]
+async fn revoke_session(
+    path: web::Path<SessionPath>,
+    auth_guard: JwtMiddleware,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::AccountAdmin)?;
+
+    let mut redis_client = data
+        .redis_client
+        .get_async_connection()
+        .await
+        .map_err(CustomError::RedisError)?;
+
+    if !owns_session(&mut redis_client, auth_guard.user.id, path.session_id)
+        .await
+        .map_err(CustomError::RedisError)?
+    {
+        return Err(CustomError::HttpError(CustomHttpError::SessionNotFound));
+    }
+
+    end_session(&mut redis_client, auth_guard.user.id, path.session_id)
+        .await
+        .map_err(CustomError::RedisError)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "message": "Session revoked successfully"
+    })))
+}
+
+/// Logs the user out everywhere, this device included.
+#[delete("/auth/sessions")]
+async fn revoke_all_sessions(
+    auth_guard: JwtMiddleware,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::AccountAdmin)?;
+
+    let mut redis_client = data
+        .redis_client
+        .get_async_connection()
+        .await
+        .map_err(CustomError::RedisError)?;
+
+    let revoked = end_all_sessions(&mut redis_client, auth_guard.user.id)
+        .await
+        .map_err(CustomError::RedisError)?;
+
+    // Covers an access token issued before sessions were tracked.
+    if let Some(access_token_uuid) = auth_guard.access_token_uuid {
+        redis_client
+            .del::<_, ()>(access_token_uuid.to_string())
+            .await
+            .map_err(CustomError::RedisError)?;
+    }
+
+    Ok(logged_out_response().json(serde_json::json!({
+        "status": "success",
+        "revoked_sessions": revoked
+    })))
+}
+
 #[get("/users/me")]
-async fn me(jwt_guard: JwtMiddleware) -> impl Responder {
+async fn me(jwt_guard: JwtMiddleware) -> Result<HttpResponse, CustomError> {
+    jwt_guard.require(Scope::AccountAdmin)?;
+
     let json_response = serde_json::json!({
         "status":  "success",
         "data": serde_json::json!(
              filter_user_record(&jwt_guard.user))
     });
 
-    HttpResponse::Ok().json(json_response)
+    Ok(HttpResponse::Ok().json(json_response))
 }
@@ -0,0 +1,280 @@
+use actix_web::{delete, get, patch, post, web, HttpResponse};
+
+use sqlx::PgPool;
+
+use uuid::Uuid;
+
+use validator::Validate;
+
+use crate::models::collection::{
+    Collection, CollectionPath, CollectionSummary, CreateCollection, DeleteCollectionQuery,
+    DeleteMode, UpdateCollection,
+};
+
+use crate::app_state::AppState;
+
+use crate::jwt_auth::JwtMiddleware;
+
+use crate::scope::Scope;
+
+use crate::custom_error::{handle_validation_error, CustomError, CustomHttpError};
+
+use crate::api::revision::record_revisions;
+
+/// Fails with `CollectionNotFound` unless the collection exists and belongs to the user.
+pub async fn ensure_collection_owned(
+    db: &PgPool,
+    user_id: Uuid,
+    collection_id: Uuid,
+) -> Result<(), CustomError> {
+    let owned = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM collections WHERE id = $1 AND user_id = $2) AS "owned!""#,
+        collection_id,
+        user_id
+    )
+    .fetch_one(db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    if !owned {
+        return Err(CustomError::HttpError(CustomHttpError::CollectionNotFound));
+    }
+    Ok(())
+}
+
+#[post("/collections")]
+pub async fn create_collection(
+    body: web::Json<CreateCollection>,
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::LinksWrite)?;
+
+    if let Err(validation_error) = body.validate() {
+        return handle_validation_error(validation_error);
+    }
+
+    if let Some(parent_id) = body.parent_id {
+        ensure_collection_owned(&data.db, auth_guard.user.id, parent_id).await?;
+    }
+
+    let collection = sqlx::query_as!(
+        Collection,
+        r#"
+        INSERT INTO collections (user_id, parent_id, name)
+        VALUES ($1, $2, $3)
+        RETURNING *
+        "#,
+        auth_guard.user.id,
+        body.parent_id,
+        body.name.trim()
+    )
+    .fetch_one(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "status": "success",
+        "data": collection
+    })))
+}
+
+#[get("/collections")]
+pub async fn get_collections(
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::LinksRead)?;
+
+    let collections = sqlx::query_as!(
+        CollectionSummary,
+        r#"
+        WITH RECURSIVE tree AS (
+            SELECT id AS root_id, id FROM collections WHERE user_id = $1
+            UNION ALL
+            SELECT tree.root_id, collections.id
+            FROM collections JOIN tree ON collections.parent_id = tree.id
+        ),
+        totals AS (
+            SELECT tree.root_id, COUNT(urls.id) AS link_count, COALESCE(SUM(urls.views), 0) AS views
+            FROM tree JOIN urls ON urls.collection_id = tree.id
+            GROUP BY tree.root_id
+        ),
+        direct AS (
+            SELECT collection_id, COUNT(*) AS link_count, COALESCE(SUM(views), 0) AS views
+            FROM urls WHERE user_id = $1 AND collection_id IS NOT NULL
+            GROUP BY collection_id
+        )
+        SELECT collections.id, collections.parent_id, collections.name,
+               COALESCE(direct.link_count, 0) AS "link_count!",
+               COALESCE(direct.views, 0)::BIGINT AS "views!",
+               COALESCE(totals.link_count, 0) AS "total_link_count!",
+               COALESCE(totals.views, 0)::BIGINT AS "total_views!",
+               collections.created_at, collections.updated_at
+        FROM collections
+        LEFT JOIN direct ON direct.collection_id = collections.id
+        LEFT JOIN totals ON totals.root_id = collections.id
+        WHERE collections.user_id = $1
+        ORDER BY collections.name
+        "#,
+        auth_guard.user.id
+    )
+    .fetch_all(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "results": collections.len(),
+        "data": collections
+    })))
+}
+
+#[patch("/collections/{collection_id}")]
+pub async fn update_collection(
+    path: web::Path<CollectionPath>,
+    body: web::Json<UpdateCollection>,
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::LinksWrite)?;
+
+    if let Err(validation_error) = body.validate() {
+        return handle_validation_error(validation_error);
+    }
+
+    ensure_collection_owned(&data.db, auth_guard.user.id, path.collection_id).await?;
+
+    if let Some(Some(parent_id)) = body.parent_id {
+        ensure_collection_owned(&data.db, auth_guard.user.id, parent_id).await?;
+
+        let inside_itself = sqlx::query_scalar!(
+            r#"
+            WITH RECURSIVE subtree AS (
+                SELECT id FROM collections WHERE id = $1
+                UNION ALL
+                SELECT collections.id FROM collections
+                JOIN subtree ON collections.parent_id = subtree.id
+            )
+            SELECT EXISTS (SELECT 1 FROM subtree WHERE id = $2) AS "inside_itself!"
+            "#,
+            path.collection_id,
+            parent_id
+        )
+        .fetch_one(&data.db)
+        .await
+        .map_err(CustomError::DataBaseError)?;
+
+        if inside_itself {
+            return Err(CustomError::HttpError(CustomHttpError::CollectionCycle));
+        }
+    }
+
+    let collection = sqlx::query_as!(
+        Collection,
+        r#"
+        UPDATE collections
+        SET name = COALESCE($1, name),
+            parent_id = CASE WHEN $2 THEN $3 ELSE parent_id END,
+            updated_at = now()
+        WHERE id = $4
+        RETURNING *
+        "#,
+        body.name.as_deref().map(str::trim),
+        body.parent_id.is_some(),
+        body.parent_id.flatten(),
+        path.collection_id
+    )
+    .fetch_one(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "data": collection
+    })))
+}
+
+#[delete("/collections/{collection_id}")]
+pub async fn delete_collection(
+    path: web::Path<CollectionPath>,
+    query: web::Query<DeleteCollectionQuery>,
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::LinksWrite)?;
+
+    let collection = sqlx::query_as!(
+        Collection,
+        r#"SELECT * FROM collections WHERE id = $1 AND user_id = $2"#,
+        path.collection_id,
+        auth_guard.user.id
+    )
+    .fetch_optional(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?
+    .ok_or(CustomError::HttpError(CustomHttpError::CollectionNotFound))?;
+
+    let mut tx = data.db.begin().await.map_err(CustomError::DataBaseError)?;
+
+    let deleted_links = match query.mode.unwrap_or_default() {
+        DeleteMode::Cascade => {
+            // Sub collections go with the foreign key, their links have to go first. The
+            // revisions of a deleted link go with it, as they do in `delete_url`.
+            sqlx::query!(
+                r#"
+                WITH RECURSIVE subtree AS (
+                    SELECT id FROM collections WHERE id = $1
+                    UNION ALL
+                    SELECT collections.id FROM collections
+                    JOIN subtree ON collections.parent_id = subtree.id
+                )
+                DELETE FROM urls WHERE collection_id IN (SELECT id FROM subtree) AND user_id = $2
+                "#,
+                collection.id,
+                auth_guard.user.id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(CustomError::DataBaseError)?
+            .rows_affected()
+        }
+        DeleteMode::Reparent => {
+            sqlx::query!(
+                r#"UPDATE collections SET parent_id = $1, updated_at = now() WHERE parent_id = $2"#,
+                collection.parent_id,
+                collection.id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(CustomError::DataBaseError)?;
+
+            let moved_links = sqlx::query_scalar!(
+                r#"UPDATE urls SET collection_id = $1 WHERE collection_id = $2 AND user_id = $3 RETURNING id"#,
+                collection.parent_id,
+                collection.id,
+                auth_guard.user.id
+            )
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(CustomError::DataBaseError)?;
+
+            record_revisions(&mut *tx, &moved_links, auth_guard.user.id).await?;
+
+            0
+        }
+    };
+
+    sqlx::query!(r#"DELETE FROM collections WHERE id = $1"#, collection.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(CustomError::DataBaseError)?;
+
+    tx.commit().await.map_err(CustomError::DataBaseError)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "message": "Collection deleted successfully",
+        "deleted_links": deleted_links
+    })))
+}
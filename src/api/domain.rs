@@ -0,0 +1,246 @@
+use actix_web::{delete, get, patch, post, web, HttpResponse};
+
+use rand::{distributions::Alphanumeric, Rng};
+
+use validator::Validate;
+
+use crate::models::domain::{normalize_host, CreateDomain, Domain, DomainPath, UpdateDomain};
+
+use crate::app_state::AppState;
+
+use crate::dns_resolver::TxtResolver;
+
+use crate::jwt_auth::JwtMiddleware;
+
+use crate::scope::Scope;
+
+use crate::custom_error::{handle_validation_error, CustomError, CustomHttpError};
+
+const VERIFICATION_TOKEN_LENGTH: usize = 32;
+
+fn domain_response(domain: &Domain) -> serde_json::Value {
+    serde_json::json!({
+        "domain": domain,
+        "verification": {
+            "type": "TXT",
+            "name": domain.verification_record_name(),
+            "value": domain.verification_record_value(),
+        }
+    })
+}
+
+fn is_unique_violation(err: &sqlx::Error) -> bool {
+    matches!(err, sqlx::Error::Database(db_err) if db_err.is_unique_violation())
+}
+
+#[post("/domains")]
+pub async fn create_domain(
+    body: web::Json<CreateDomain>,
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::LinksWrite)?;
+    auth_guard.require_verified_email()?;
+
+    let hostname = normalize_host(&body.hostname);
+
+    if let Err(validation_error) = (CreateDomain {
+        hostname: hostname.clone(),
+    })
+    .validate()
+    {
+        return handle_validation_error(validation_error);
+    }
+
+    if hostname == normalize_host(&data.secrets.domain) {
+        return Err(CustomError::HttpError(CustomHttpError::DomainAlreadyExists));
+    }
+
+    let verification_token: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(VERIFICATION_TOKEN_LENGTH)
+        .map(char::from)
+        .collect();
+
+    let domain = sqlx::query_as!(
+        Domain,
+        r#"
+        INSERT INTO domains (user_id, hostname, verification_token)
+        VALUES ($1, $2, $3)
+        RETURNING *
+        "#,
+        auth_guard.user.id,
+        hostname,
+        verification_token
+    )
+    .fetch_one(&data.db)
+    .await
+    .map_err(|err| {
+        if is_unique_violation(&err) {
+            CustomError::HttpError(CustomHttpError::DomainAlreadyExists)
+        } else {
+            CustomError::DataBaseError(err)
+        }
+    })?;
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "status": "success",
+        "data": domain_response(&domain)
+    })))
+}
+
+#[get("/domains")]
+pub async fn get_domains(
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::LinksRead)?;
+
+    let domains = sqlx::query_as!(
+        Domain,
+        r#"SELECT * FROM domains WHERE user_id = $1 ORDER BY created_at DESC"#,
+        auth_guard.user.id
+    )
+    .fetch_all(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    let domains: Vec<serde_json::Value> = domains.iter().map(domain_response).collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({"status": "success", "data": domains})))
+}
+
+/// Whether the TXT record holding the domain's verification token is published.
+pub async fn has_verification_record(
+    resolver: &dyn TxtResolver,
+    domain: &Domain,
+) -> Result<bool, CustomError> {
+    let records = resolver
+        .txt_records(&domain.verification_record_name())
+        .await?;
+
+    let expected = domain.verification_record_value();
+    Ok(records.iter().any(|record| record.trim() == expected))
+}
+
+#[post("/domains/{domain_id}/verify")]
+pub async fn verify_domain(
+    path: web::Path<DomainPath>,
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::LinksWrite)?;
+
+    let domain = sqlx::query_as!(
+        Domain,
+        r#"SELECT * FROM domains WHERE id = $1 AND user_id = $2"#,
+        path.domain_id,
+        auth_guard.user.id
+    )
+    .fetch_optional(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?
+    .ok_or(CustomError::HttpError(CustomHttpError::DomainNotFound))?;
+
+    if domain.verified_at.is_some() {
+        return Ok(HttpResponse::Ok().json(serde_json::json!({
+            "status": "success",
+            "data": domain_response(&domain)
+        })));
+    }
+
+    if !has_verification_record(data.dns_resolver.as_ref(), &domain).await? {
+        return Err(CustomError::HttpError(
+            CustomHttpError::DomainVerificationFailed,
+        ));
+    }
+
+    let domain = sqlx::query_as!(
+        Domain,
+        r#"
+        UPDATE domains SET verified_at = now(), updated_at = now()
+        WHERE id = $1
+        RETURNING *
+        "#,
+        domain.id
+    )
+    .fetch_one(&data.db)
+    .await
+    .map_err(|err| {
+        if is_unique_violation(&err) {
+            CustomError::HttpError(CustomHttpError::DomainAlreadyExists)
+        } else {
+            CustomError::DataBaseError(err)
+        }
+    })?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "data": domain_response(&domain)
+    })))
+}
+
+#[patch("/domains/{domain_id}")]
+pub async fn update_domain(
+    path: web::Path<DomainPath>,
+    body: web::Json<UpdateDomain>,
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::LinksWrite)?;
+
+    let domain = sqlx::query_as!(
+        Domain,
+        r#"
+        UPDATE domains
+        SET case_insensitive = COALESCE($1, case_insensitive), updated_at = now()
+        WHERE id = $2 AND user_id = $3
+        RETURNING *
+        "#,
+        body.case_insensitive,
+        path.domain_id,
+        auth_guard.user.id
+    )
+    .fetch_optional(&data.db)
+    .await
+    .map_err(|err| {
+        if is_unique_violation(&err) {
+            CustomError::HttpError(CustomHttpError::ShortUrlCaseCollision)
+        } else {
+            CustomError::DataBaseError(err)
+        }
+    })?
+    .ok_or(CustomError::HttpError(CustomHttpError::DomainNotFound))?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "data": domain_response(&domain)
+    })))
+}
+
+#[delete("/domains/{domain_id}")]
+pub async fn delete_domain(
+    path: web::Path<DomainPath>,
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::LinksWrite)?;
+
+    let delete_result = sqlx::query!(
+        r#"DELETE FROM domains WHERE id = $1 AND user_id = $2"#,
+        path.domain_id,
+        auth_guard.user.id
+    )
+    .execute(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    if delete_result.rows_affected() == 0 {
+        return Err(CustomError::HttpError(CustomHttpError::DomainNotFound));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "message": "Domain deleted successfully"
+    })))
+}
@@ -0,0 +1,238 @@
+use actix_web::{get, http::header, web, HttpResponse};
+
+use futures::{
+    channel::{mpsc, oneshot},
+    SinkExt, StreamExt,
+};
+
+use serde_json::{Map, Value};
+
+use tracing::error;
+
+use crate::models::url::{ExportFormat, ExportQuery, Url, UrlRecord};
+
+use crate::app_state::AppState;
+
+use crate::jwt_auth::JwtMiddleware;
+
+use crate::scope::Scope;
+
+use crate::custom_error::{CustomError, ValidationModelsErrors};
+
+pub(crate) const EXPORT_COLUMNS: [&str; 26] = [
+    "id",
+    "user_id",
+    "domain_id",
+    "collection_id",
+    "original_url",
+    "short_url",
+    "views",
+    "category",
+    "slug",
+    "title",
+    "notes",
+    "is_favorite",
+    "flagged_at",
+    "flag_reason",
+    "meta_title",
+    "meta_description",
+    "meta_image_url",
+    "meta_favicon_url",
+    "metadata_fetched_at",
+    "health_status_code",
+    "health_latency_ms",
+    "health_error",
+    "health_checked_at",
+    "health_failures",
+    "created_at",
+    "updated_at",
+];
+
+pub(crate) fn parse_columns(columns: Option<&str>) -> Result<Vec<String>, CustomError> {
+    let columns = match columns {
+        Some(columns) if !columns.trim().is_empty() => columns,
+        _ => return Ok(EXPORT_COLUMNS.iter().map(|c| c.to_string()).collect()),
+    };
+
+    columns
+        .split(',')
+        .map(|column| column.trim())
+        .filter(|column| !column.is_empty())
+        .map(|column| {
+            if EXPORT_COLUMNS.contains(&column) {
+                Ok(column.to_string())
+            } else {
+                Err(CustomError::ValidationError(ValidationModelsErrors::Error(
+                    format!(
+                        "Unknown column '{}', valid columns are: {}",
+                        column,
+                        EXPORT_COLUMNS.join(", ")
+                    ),
+                )))
+            }
+        })
+        .collect()
+}
+
+pub(crate) struct ExportWriter {
+    format: ExportFormat,
+    columns: Vec<String>,
+    rows_written: usize,
+}
+
+impl ExportWriter {
+    pub(crate) fn new(format: ExportFormat, columns: Vec<String>) -> Self {
+        Self {
+            format,
+            columns,
+            rows_written: 0,
+        }
+    }
+
+    pub(crate) fn header(&self) -> Result<web::Bytes, CustomError> {
+        match self.format {
+            ExportFormat::Csv => csv_line(self.columns.iter().map(String::as_str)),
+            ExportFormat::Json => Ok(web::Bytes::from_static(b"[")),
+            ExportFormat::Ndjson => Ok(web::Bytes::new()),
+        }
+    }
+
+    pub(crate) fn row(&mut self, record: &UrlRecord) -> Result<web::Bytes, CustomError> {
+        let value =
+            serde_json::to_value(record).map_err(|err| CustomError::OtherError(err.to_string()))?;
+
+        let chunk = match self.format {
+            ExportFormat::Csv => {
+                let fields: Vec<String> = self
+                    .columns
+                    .iter()
+                    .map(|column| csv_field(&value[column.as_str()]))
+                    .collect();
+                csv_line(fields.iter().map(String::as_str))?
+            }
+            ExportFormat::Json | ExportFormat::Ndjson => {
+                let mut selected = Map::new();
+                for column in &self.columns {
+                    selected.insert(column.clone(), value[column.as_str()].clone());
+                }
+                let object = Value::Object(selected);
+
+                match self.format {
+                    ExportFormat::Json if self.rows_written > 0 => format!(",{}", object),
+                    ExportFormat::Json => object.to_string(),
+                    _ => format!("{}\n", object),
+                }
+                .into()
+            }
+        };
+
+        self.rows_written += 1;
+        Ok(chunk)
+    }
+
+    pub(crate) fn footer(&self) -> web::Bytes {
+        match self.format {
+            ExportFormat::Json => web::Bytes::from_static(b"]"),
+            _ => web::Bytes::new(),
+        }
+    }
+}
+
+fn csv_field(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(value) => value.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn csv_line<'a>(fields: impl Iterator<Item = &'a str>) -> Result<web::Bytes, CustomError> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record(fields)
+        .map_err(|err| CustomError::OtherError(err.to_string()))?;
+    let line = writer
+        .into_inner()
+        .map_err(|err| CustomError::OtherError(err.to_string()))?;
+    Ok(web::Bytes::from(line))
+}
+
+/// The status line goes out before the rows, so it waits for the first row: a query that
+/// fails to start gets a proper error response. A failure after that cannot change the
+/// status anymore, the connection is cut before the final chunk so clients see an
+/// incomplete transfer instead of a file that merely looks short.
+#[get("/url/export")]
+pub async fn export_url_records(
+    data: web::Data<AppState>,
+    query: web::Query<ExportQuery>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::LinksRead)?;
+
+    let format = query.format.unwrap_or(ExportFormat::Json);
+    let columns = parse_columns(query.columns.as_deref())?;
+
+    let mut writer = ExportWriter::new(format, columns);
+    let header = writer.header()?;
+
+    let (mut tx, rx) = mpsc::channel::<Result<web::Bytes, CustomError>>(32);
+    let (started_tx, started_rx) = oneshot::channel::<Result<(), CustomError>>();
+    let pool = data.db.clone();
+    let user_id = auth_guard.user.id;
+
+    actix_web::rt::spawn(async move {
+        let mut rows = sqlx::query_as!(
+            Url,
+            r#"
+            SELECT id, original_url, short_url, user_id, domain_id, collection_id, views, category, slug, title, notes, is_favorite, flagged_at, flag_reason, meta_title, meta_description, meta_image_url, meta_favicon_url, metadata_fetched_at, health_status_code, health_latency_ms, health_error, health_checked_at, health_failures, case_insensitive, created_at, updated_at
+            FROM urls
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            "#,
+            user_id
+        )
+        .fetch(&pool);
+
+        let first = rows.next().await;
+        if let Some(Err(err)) = first {
+            let _ = started_tx.send(Err(CustomError::DataBaseError(err)));
+            return;
+        }
+        if started_tx.send(Ok(())).is_err() || tx.send(Ok(header)).await.is_err() {
+            return;
+        }
+
+        let mut rows = futures::stream::iter(first).chain(rows);
+        while let Some(row) = rows.next().await {
+            let chunk = row
+                .map_err(CustomError::DataBaseError)
+                .and_then(|record| writer.row(&UrlRecord::new(user_id, record)));
+
+            if let Err(err) = &chunk {
+                error!(
+                    "Export for user {} failed after {} rows: {}",
+                    user_id, writer.rows_written, err
+                );
+            }
+
+            let failed = chunk.is_err();
+            if tx.send(chunk).await.is_err() || failed {
+                return;
+            }
+        }
+
+        let _ = tx.send(Ok(writer.footer())).await;
+    });
+
+    started_rx.await.map_err(|_| {
+        CustomError::OtherError("The export stopped before it started".to_string())
+    })??;
+
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"urls.{}\"", format.extension()),
+        ))
+        .streaming(rx))
+}
@@ -1,15 +1,44 @@
 use actix_cors::Cors;
 use actix_web::{http::header, web};
 
-use super::auth::{login, logout, me, refresh_access_token, register};
+use super::api_key::{create_api_key, get_api_keys, revoke_api_key};
+
+use super::admin::{get_redirect_loops, get_short_url_case_collisions, get_short_url_misses};
+
+use super::auth::{
+    get_sessions, login, logout, me, refresh_access_token, register, resend_verification_email,
+    revoke_all_sessions, revoke_session, verify_email,
+};
 
 use super::url::{
-    create_url, delete_url, get_all_url_record, get_url_by_id, redirect_to_original_url, update_url,
+    create_url, delete_url, get_all_url_record, get_broken_urls, get_url_by_id, get_url_qr_code,
+    redirect_by_host, redirect_to_original_url, refresh_url_metadata, update_url,
 };
 
+use super::collection::{create_collection, delete_collection, get_collections, update_collection};
+
+use super::domain::{create_domain, delete_domain, get_domains, update_domain, verify_domain};
+
+use super::export::export_url_records;
+
 use super::health_route::health_checker;
+
+use super::oidc::{oidc_callback, oidc_login};
+
+use super::password_reset::{forgot_password, reset_password};
+
+use super::revision::{get_url_revisions, rollback_url_revision};
+
+use super::import::import_urls;
+
+use super::two_factor::{confirm_two_factor, disable_two_factor, enroll_two_factor, login_mfa};
+
+use super::user::{change_password, delete_me, update_me};
 use crate::config_env;
 
+/// Import files are sent as raw bodies, which actix caps at 256kB by default.
+const IMPORT_PAYLOAD_LIMIT: usize = 16 * 1024 * 1024;
+
 pub fn config_handler(config: &mut web::ServiceConfig, config_data: &config_env::Config) {
     let cors = Cors::default()
         .allowed_origin(&config_data.client_origin)
@@ -19,6 +48,7 @@ pub fn config_handler(config: &mut web::ServiceConfig, config_data: &config_env:
             header::CONTENT_TYPE,
             header::ACCEPT,
             header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            header::HeaderName::from_static("x-api-key"),
         ])
         .supports_credentials()
         .max_age(3600);
@@ -29,14 +59,56 @@ pub fn config_handler(config: &mut web::ServiceConfig, config_data: &config_env:
         .service(create_url)
         .service(delete_url)
         .service(get_all_url_record)
+        .service(export_url_records)
+        .service(
+            web::resource("/url/import")
+                .app_data(web::PayloadConfig::new(IMPORT_PAYLOAD_LIMIT))
+                .route(web::post().to(import_urls)),
+        )
+        .service(get_broken_urls)
         .service(get_url_by_id)
+        .service(get_url_qr_code)
+        .service(refresh_url_metadata)
+        .service(get_url_revisions)
+        .service(rollback_url_revision)
         .service(redirect_to_original_url)
         .service(update_url)
         .service(register)
         .service(me)
+        .service(update_me)
+        .service(change_password)
+        .service(delete_me)
         .service(login)
+        .service(login_mfa)
+        .service(oidc_login)
+        .service(oidc_callback)
+        .service(enroll_two_factor)
+        .service(confirm_two_factor)
+        .service(disable_two_factor)
         .service(logout)
-        .service(refresh_access_token);
+        .service(refresh_access_token)
+        .service(verify_email)
+        .service(resend_verification_email)
+        .service(forgot_password)
+        .service(reset_password)
+        .service(get_sessions)
+        .service(revoke_session)
+        .service(revoke_all_sessions)
+        .service(create_domain)
+        .service(get_domains)
+        .service(verify_domain)
+        .service(update_domain)
+        .service(delete_domain)
+        .service(create_api_key)
+        .service(get_api_keys)
+        .service(revoke_api_key)
+        .service(create_collection)
+        .service(get_collections)
+        .service(update_collection)
+        .service(delete_collection)
+        .service(get_redirect_loops)
+        .service(get_short_url_case_collisions)
+        .service(get_short_url_misses);
 
-    config.service(scope);
+    config.service(scope).service(redirect_by_host);
 }
@@ -0,0 +1,143 @@
+use actix_web::{web, HttpResponse};
+
+use validator::validate_url;
+
+use crate::models::user::User;
+
+use crate::models::url::{
+    ImportQuery, ImportReport, RenamedLink, SkippedLink, UrlCategory, SHORT_URL_REGEX,
+};
+
+use crate::app_state::AppState;
+
+use crate::jwt_auth::JwtMiddleware;
+
+use crate::scope::Scope;
+
+use crate::custom_error::CustomError;
+
+use crate::importers::{parse_import, ImportedLink};
+
+use crate::api::revision::record_revision;
+
+use crate::api::url::short_url_taken_ignoring_case;
+
+use crate::url_safety::UrlVerdict;
+
+use crate::utils::{
+    short_code::{generate_short_code, validate_short_code},
+    slugify::slugify,
+};
+
+const MAX_SHORT_CODE_ATTEMPTS: usize = 5;
+
+/// `POST /url/import`, registered in `config_handler` with its own payload limit.
+pub async fn import_urls(
+    body: web::Bytes,
+    query: web::Query<ImportQuery>,
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::LinksWrite)?;
+    auth_guard.require_verified_email()?;
+
+    let links = parse_import(query.source, &body)?;
+    let report = import_links(&data, &auth_guard.user, auth_guard.acts_as_admin(), links).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({"status": "success", "data": report})))
+}
+
+/// Creates the parsed links for `user` and reports what became of each of them.
+/// Reserved short codes are only kept when `allow_reserved` is set.
+pub(crate) async fn import_links(
+    data: &AppState,
+    user: &User,
+    allow_reserved: bool,
+    links: Vec<ImportedLink>,
+) -> Result<ImportReport, CustomError> {
+    let mut report = ImportReport {
+        total: links.len(),
+        ..Default::default()
+    };
+
+    'links: for link in links {
+        if !validate_url(&link.original_url) {
+            report.skipped.push(SkippedLink {
+                row: link.row,
+                reason: format!("Invalid destination URL '{}'", link.original_url),
+            });
+            continue;
+        }
+
+        if let UrlVerdict::Blocked(reason) = data.url_safety.check(&link.original_url).await? {
+            report.skipped.push(SkippedLink {
+                row: link.row,
+                reason: format!("Destination URL is blocked: {}", reason),
+            });
+            continue;
+        }
+
+        let requested = link
+            .short_url
+            .clone()
+            .filter(|code| SHORT_URL_REGEX.is_match(code))
+            .filter(|code| validate_short_code(&data.secrets, code, allow_reserved).is_ok());
+        let mut short_url = match requested.clone() {
+            Some(code) => code,
+            None => generate_short_code(&data.secrets)?,
+        };
+
+        for attempt in 1..=MAX_SHORT_CODE_ATTEMPTS {
+            // A code differing by case is taken as well when lookups ignore case. The
+            // unique indexes catch it too, checking first only spares the attempt.
+            let inserted = if short_url_taken_ignoring_case(data, None, &short_url, None).await? {
+                None
+            } else {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO urls (original_url, short_url, user_id, views, category, slug, created_at, updated_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, COALESCE($7, now()), COALESCE($7, now()))
+                    ON CONFLICT DO NOTHING
+                    RETURNING id
+                    "#,
+                    link.original_url,
+                    short_url,
+                    user.id,
+                    link.views,
+                    UrlCategory::All.to_string(),
+                    slugify(&short_url),
+                    link.created_at
+                )
+                .fetch_optional(&data.db)
+                .await
+                .map_err(CustomError::DataBaseError)?
+            };
+
+            if let Some(inserted) = inserted {
+                record_revision(&data.db, inserted.id, user.id).await?;
+                break;
+            }
+
+            if attempt == MAX_SHORT_CODE_ATTEMPTS {
+                report.skipped.push(SkippedLink {
+                    row: link.row,
+                    reason: "Could not allocate a free short URL".to_string(),
+                });
+                continue 'links;
+            }
+            short_url = generate_short_code(&data.secrets)?;
+        }
+
+        report.imported += 1;
+        match (requested, link.short_url) {
+            (Some(requested), _) if requested == short_url => report.preserved += 1,
+            (_, Some(original_code)) => report.renamed.push(RenamedLink {
+                original_code,
+                short_url,
+            }),
+            _ => {}
+        }
+    }
+
+    Ok(report)
+}
@@ -1,5 +1,16 @@
+pub mod admin;
+pub mod api_key;
 pub mod auth;
+pub mod collection;
+pub mod domain;
+pub mod export;
 pub mod handler;
 pub mod health_route;
+pub mod import;
+pub mod oidc;
+pub mod password_reset;
 pub mod reponse;
+pub mod revision;
+pub mod two_factor;
 pub mod url;
+pub mod user;
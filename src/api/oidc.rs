@@ -0,0 +1,282 @@
+use actix_web::{
+    cookie::{time::Duration as ActixWebDuration, Cookie, SameSite},
+    get,
+    http::header,
+    web, HttpRequest, HttpResponse,
+};
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
+    Argon2,
+};
+
+use rand::{distributions::Alphanumeric, Rng};
+
+use redis::AsyncCommands;
+
+use serde::{Deserialize, Serialize};
+
+use tracing::info;
+
+use crate::models::oidc::OidcCallbackQuery;
+
+use crate::models::user::User;
+
+use super::auth::complete_login;
+
+use crate::app_state::AppState;
+
+use crate::oidc::{IdTokenClaims, OidcClient};
+
+use crate::token::session::ClientInfo;
+
+use crate::custom_error::{CustomError, CustomHttpError};
+
+/// Seconds the user has to come back from the provider.
+const OIDC_STATE_MAX_AGE: usize = 10 * 60;
+
+/// Holds the `state` of the login the browser started.
+const OIDC_STATE_COOKIE: &str = "oidc_state";
+
+/// Kept in Redis under the `state` sent to the provider, until the callback.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct PendingLogin {
+    pub(crate) nonce: String,
+    pub(crate) code_verifier: String,
+}
+
+/// Only sent back to the callback. `Lax` still lets it through on the redirect from the
+/// provider, which is a top-level navigation.
+pub(crate) fn state_cookie(state: &str) -> Cookie<'static> {
+    Cookie::build(OIDC_STATE_COOKIE, state.to_string())
+        .path("/api/auth/oidc")
+        .max_age(ActixWebDuration::seconds(OIDC_STATE_MAX_AGE as i64))
+        .http_only(true)
+        .secure(cfg!(not(debug_assertions)))
+        .same_site(SameSite::Lax)
+        .finish()
+}
+
+/// The callback must come back to the browser that started the login. A valid state is
+/// not enough, anyone could otherwise log a victim into their own account by sending
+/// them the callback URL of a login they started themselves.
+pub(crate) fn check_state_cookie(cookie: Option<&str>, state: &str) -> Result<(), CustomError> {
+    match cookie {
+        Some(cookie) if !cookie.is_empty() && cookie == state => Ok(()),
+        _ => Err(CustomError::HttpError(CustomHttpError::OidcStateInvalid)),
+    }
+}
+
+fn oidc_client(data: &AppState) -> Result<&OidcClient, CustomError> {
+    data.oidc
+        .as_deref()
+        .ok_or(CustomError::HttpError(CustomHttpError::OidcNotConfigured))
+}
+
+/// Sends the browser to the provider's login page.
+#[get("/auth/oidc/login")]
+pub async fn oidc_login(data: web::Data<AppState>) -> Result<HttpResponse, CustomError> {
+    let request = oidc_client(&data)?.authorization_request().await?;
+
+    let pending = serde_json::to_string(&PendingLogin {
+        nonce: request.nonce,
+        code_verifier: request.code_verifier,
+    })
+    .map_err(|err| CustomError::OtherError(err.to_string()))?;
+
+    let mut redis_client = data
+        .redis_client
+        .get_async_connection()
+        .await
+        .map_err(CustomError::RedisError)?;
+
+    redis_client
+        .set_ex::<_, _, ()>(
+            format!("oidc_state:{}", request.state),
+            pending,
+            OIDC_STATE_MAX_AGE,
+        )
+        .await
+        .map_err(CustomError::RedisError)?;
+
+    Ok(HttpResponse::Found()
+        .insert_header((header::LOCATION, request.url))
+        .cookie(state_cookie(&request.state))
+        .finish())
+}
+
+/// Where the provider sends the browser back, logs the user in like `login` does.
+#[get("/auth/oidc/callback")]
+pub async fn oidc_callback(
+    req: HttpRequest,
+    query: web::Query<OidcCallbackQuery>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let client = oidc_client(&data)?;
+
+    check_state_cookie(
+        req.cookie(OIDC_STATE_COOKIE)
+            .as_ref()
+            .map(|cookie| cookie.value()),
+        &query.state,
+    )?;
+
+    let mut redis_client = data
+        .redis_client
+        .get_async_connection()
+        .await
+        .map_err(CustomError::RedisError)?;
+
+    // Taken in one step, so a state is only ever used once.
+    let pending: Option<String> = redis_client
+        .get_del(format!("oidc_state:{}", query.state))
+        .await
+        .map_err(CustomError::RedisError)?;
+    let pending: PendingLogin = pending
+        .and_then(|pending| serde_json::from_str(&pending).ok())
+        .ok_or(CustomError::HttpError(CustomHttpError::OidcStateInvalid))?;
+
+    let claims = verify_callback(client, &query, &pending).await?;
+
+    let user = find_or_link_user(&data, &claims).await?;
+
+    let ip = ClientInfo::from_request(&req, &data.secrets)
+        .ip
+        .unwrap_or_default();
+    info!(target: "security", email = %user.email, %ip, issuer = %claims.iss, outcome = "oidc_success", "Login attempt");
+
+    let mut response = complete_login(&req, &data, &user).await?;
+    response
+        .add_removal_cookie(&state_cookie(""))
+        .map_err(|err| CustomError::OtherError(err.to_string()))?;
+
+    Ok(response)
+}
+
+/// Trades the code from the callback for the user's claims, which must carry the nonce
+/// of the pending login.
+pub(crate) async fn verify_callback(
+    client: &OidcClient,
+    query: &OidcCallbackQuery,
+    pending: &PendingLogin,
+) -> Result<IdTokenClaims, CustomError> {
+    if let Some(error) = &query.error {
+        return Err(CustomError::HttpError(CustomHttpError::OidcProviderError(
+            error.to_string(),
+        )));
+    }
+    let code = query
+        .code
+        .as_deref()
+        .ok_or(CustomError::HttpError(CustomHttpError::OidcStateInvalid))?;
+
+    let id_token = client.exchange_code(code, &pending.code_verifier).await?;
+    client.verify_id_token(&id_token, &pending.nonce).await
+}
+
+/// The user behind an identity at the provider. An identity seen for the first time is
+/// linked to the account with the same email, or gets a new account, but only when the
+/// provider vouches for the email, anyone could otherwise take over an account.
+pub(crate) async fn find_or_link_user(
+    data: &AppState,
+    claims: &IdTokenClaims,
+) -> Result<User, CustomError> {
+    let linked = sqlx::query_as!(
+        User,
+        r#"
+        SELECT users.* FROM users
+        JOIN user_identities ON user_identities.user_id = users.id
+        WHERE user_identities.issuer = $1 AND user_identities.subject = $2
+        "#,
+        claims.iss,
+        claims.sub
+    )
+    .fetch_optional(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    if let Some(user) = linked {
+        return Ok(user);
+    }
+
+    let email = match &claims.email {
+        Some(email) if claims.email_verified => email.to_lowercase(),
+        _ => {
+            return Err(CustomError::HttpError(
+                CustomHttpError::OidcEmailNotVerified,
+            ))
+        }
+    };
+
+    let mut tx = data.db.begin().await.map_err(CustomError::DataBaseError)?;
+
+    let existing = sqlx::query_as!(
+        User,
+        r#"
+        UPDATE users SET email_verified_at = COALESCE(email_verified_at, now()), updated_at = now()
+        WHERE email = $1
+        RETURNING *
+        "#,
+        email
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    let user = match existing {
+        Some(user) => user,
+        None => {
+            // Nobody knows this password, the account signs in through the provider until
+            // a password is set with a reset link.
+            let password: String = rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(64)
+                .map(char::from)
+                .collect();
+            let hash_pass = Argon2::default()
+                .hash_password(password.as_bytes(), &SaltString::generate(&mut OsRng))
+                .map_err(|err| CustomError::OtherError(err.to_string()))?
+                .to_string();
+
+            let name = claims
+                .name
+                .clone()
+                .filter(|name| !name.trim().is_empty())
+                .unwrap_or_else(|| email.split('@').next().unwrap_or_default().to_string());
+
+            sqlx::query_as!(
+                User,
+                r#"
+                INSERT INTO users (name, email, password, email_verified_at)
+                VALUES ($1, $2, $3, now())
+                RETURNING *
+                "#,
+                name,
+                email,
+                hash_pass
+            )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(CustomError::DataBaseError)?
+        }
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO user_identities (user_id, issuer, subject, email)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (issuer, subject) DO NOTHING
+        "#,
+        user.id,
+        claims.iss,
+        claims.sub,
+        email
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    tx.commit().await.map_err(CustomError::DataBaseError)?;
+
+    Ok(user)
+}
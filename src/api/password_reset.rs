@@ -0,0 +1,174 @@
+use actix_web::{post, web, HttpResponse};
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
+    Argon2,
+};
+
+use rand::{distributions::Alphanumeric, Rng};
+
+use sha2::{Digest, Sha256};
+
+use tracing::warn;
+
+use validator::Validate;
+
+use crate::models::user::{ForgotPasswordSchema, ResetPasswordSchema, User};
+
+use crate::app_state::AppState;
+
+use crate::config_env::Config;
+
+use crate::mailer::Email;
+
+use crate::token::session::end_all_sessions;
+
+use crate::custom_error::{handle_validation_error, CustomError, CustomHttpError};
+
+const RESET_TOKEN_LENGTH: usize = 48;
+
+fn hash_reset_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+pub(crate) fn password_reset_email(config: &Config, user: &User, token: &str) -> Email {
+    Email {
+        to: user.email.clone(),
+        subject: "Reset your password".to_string(),
+        body: format!(
+            "Hi {},\n\nSomeone asked to reset the password of your account. If it was you, open this link to choose a new one:\n\n{}/reset-password?token={}\n\nThe link expires in {} minutes. If you did not ask for it, you can ignore this email.\n",
+            user.name, config.client_origin, token, config.password_reset_token_max_age
+        ),
+    }
+}
+
+/// Always answers the same way, so the endpoint cannot be used to find out who has an account.
+#[post("/auth/forgot-password")]
+pub async fn forgot_password(
+    body: web::Json<ForgotPasswordSchema>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    if let Err(validation_error) = body.validate() {
+        return handle_validation_error(validation_error);
+    }
+
+    let user = sqlx::query_as!(
+        User,
+        r#"SELECT * FROM users WHERE email = $1"#,
+        body.email.to_lowercase()
+    )
+    .fetch_optional(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    if let Some(user) = user {
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(RESET_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+
+        let mut tx = data.db.begin().await.map_err(CustomError::DataBaseError)?;
+
+        // Only the latest link works.
+        sqlx::query!(
+            r#"UPDATE password_reset_tokens SET used_at = now() WHERE user_id = $1 AND used_at IS NULL"#,
+            user.id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(CustomError::DataBaseError)?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO password_reset_tokens (user_id, token_hash, expires_at)
+            VALUES ($1, $2, now() + make_interval(mins => $3))
+            "#,
+            user.id,
+            hash_reset_token(&token),
+            data.secrets.password_reset_token_max_age as i32
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(CustomError::DataBaseError)?;
+
+        tx.commit().await.map_err(CustomError::DataBaseError)?;
+
+        let email = password_reset_email(&data.secrets, &user, &token);
+
+        // Sent in the background, a slow mail server would otherwise give the account away.
+        let mailer = data.mailer.clone();
+        actix_web::rt::spawn(async move {
+            if let Err(err) = mailer.send(&email).await {
+                warn!("Failed to send the password reset email: {}", err);
+            }
+        });
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "message": "If an account exists for this email, a link to reset the password was sent to it"
+    })))
+}
+
+#[post("/auth/reset-password")]
+pub async fn reset_password(
+    body: web::Json<ResetPasswordSchema>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    if let Err(validation_error) = body.validate() {
+        return handle_validation_error(validation_error);
+    }
+
+    let mut tx = data.db.begin().await.map_err(CustomError::DataBaseError)?;
+
+    let user_id = sqlx::query_scalar!(
+        r#"
+        UPDATE password_reset_tokens SET used_at = now()
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > now()
+        RETURNING user_id
+        "#,
+        hash_reset_token(&body.token)
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(CustomError::DataBaseError)?
+    .ok_or(CustomError::HttpError(CustomHttpError::ResetTokenInvalid))?;
+
+    let salt = SaltString::generate(&mut OsRng);
+    let hash_pass = Argon2::default()
+        .hash_password(body.password.as_bytes(), &salt)
+        .map_err(|err| CustomError::OtherError(err.to_string()))?
+        .to_string();
+
+    // The link came by email, which proves the address as well.
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET password = $1, email_verified_at = COALESCE(email_verified_at, now()), updated_at = now()
+        WHERE id = $2
+        "#,
+        hash_pass,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    tx.commit().await.map_err(CustomError::DataBaseError)?;
+
+    let mut redis_client = data
+        .redis_client
+        .get_async_connection()
+        .await
+        .map_err(CustomError::RedisError)?;
+
+    end_all_sessions(&mut redis_client, user_id)
+        .await
+        .map_err(CustomError::RedisError)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "message": "Password changed, please login again"
+    })))
+}
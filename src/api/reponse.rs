@@ -9,6 +9,8 @@ pub struct FilteredUser {
     pub id: String,
     pub name: String,
     pub email: String,
+    pub emailVerifiedAt: Option<chrono::DateTime<chrono::Utc>>,
+    pub twoFactorEnabled: bool,
     pub createdAt: Option<chrono::DateTime<chrono::Utc>>,
     pub updatedAt: Option<chrono::DateTime<chrono::Utc>>,
 }
@@ -29,6 +31,8 @@ pub fn filter_user_record(user: &User) -> UserData {
         id: user.id.to_string(),
         email: user.email.to_owned(),
         name: user.name.to_owned(),
+        emailVerifiedAt: user.email_verified_at,
+        twoFactorEnabled: user.totp_enabled_at.is_some(),
         createdAt: Some(user.created_at.unwrap()),
         updatedAt: Some(user.updated_at.unwrap()),
     };
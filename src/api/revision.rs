@@ -0,0 +1,215 @@
+use actix_web::{get, post, web, HttpResponse};
+
+use sqlx::PgExecutor;
+
+use uuid::Uuid;
+
+use crate::models::url::{RevisionPath, UrlPath, UrlRevision};
+
+use crate::app_state::AppState;
+
+use crate::jwt_auth::JwtMiddleware;
+
+use crate::scope::Scope;
+
+use crate::custom_error::{CustomError, CustomHttpError};
+
+use crate::api::collection::ensure_collection_owned;
+
+use crate::api::url::short_url_taken_ignoring_case;
+
+use crate::link_metadata::spawn_metadata_refresh;
+
+use crate::redirect_chain::{check_redirect_chain, LinkKey};
+
+use crate::url_safety::ensure_url_is_safe;
+
+use crate::utils::short_code::validate_short_code;
+
+use crate::utils::slugify::slugify;
+
+/// Snapshots the current state of a link, to be called after every change made to it.
+pub async fn record_revision<'e>(
+    executor: impl PgExecutor<'e>,
+    url_id: Uuid,
+    changed_by: Uuid,
+) -> Result<(), CustomError> {
+    record_revisions(executor, &[url_id], changed_by).await
+}
+
+/// Same as `record_revision` for every link in `url_ids`, for changes made to many at once.
+pub async fn record_revisions<'e>(
+    executor: impl PgExecutor<'e>,
+    url_ids: &[Uuid],
+    changed_by: Uuid,
+) -> Result<(), CustomError> {
+    sqlx::query!(
+        r#"
+        INSERT INTO url_revisions (url_id, changed_by, original_url, short_url, category, title, notes, is_favorite, collection_id)
+        SELECT id, $2, original_url, short_url, category, title, notes, is_favorite, collection_id
+        FROM urls WHERE id = ANY($1)
+        "#,
+        url_ids,
+        changed_by
+    )
+    .execute(executor)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    Ok(())
+}
+
+async fn ensure_url_owned(data: &AppState, url_id: Uuid, user_id: Uuid) -> Result<(), CustomError> {
+    let owned = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM urls WHERE id = $1 AND user_id = $2) AS "owned!""#,
+        url_id,
+        user_id
+    )
+    .fetch_one(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    if !owned {
+        return Err(CustomError::HttpError(CustomHttpError::UrlNotFound));
+    }
+    Ok(())
+}
+
+#[get("/url/{url_id}/revisions")]
+pub async fn get_url_revisions(
+    path: web::Path<UrlPath>,
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::LinksRead)?;
+
+    ensure_url_owned(&data, path.url_id, auth_guard.user.id).await?;
+
+    let revisions = sqlx::query_as!(
+        UrlRevision,
+        r#"SELECT * FROM url_revisions WHERE url_id = $1 ORDER BY created_at DESC"#,
+        path.url_id
+    )
+    .fetch_all(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "results": revisions.len(),
+        "data": revisions
+    })))
+}
+
+/// Puts the link back in the state of a revision. The rollback is itself recorded
+/// as a new revision, so it can be undone the same way.
+#[post("/url/{url_id}/revisions/{revision_id}/rollback")]
+pub async fn rollback_url_revision(
+    path: web::Path<RevisionPath>,
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::LinksWrite)?;
+
+    let current = sqlx::query!(
+        r#"SELECT domain_id, short_url, original_url FROM urls WHERE id = $1 AND user_id = $2"#,
+        path.url_id,
+        auth_guard.user.id
+    )
+    .fetch_optional(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?
+    .ok_or(CustomError::HttpError(CustomHttpError::UrlNotFound))?;
+
+    let revision = sqlx::query_as!(
+        UrlRevision,
+        r#"SELECT * FROM url_revisions WHERE id = $1 AND url_id = $2"#,
+        path.revision_id,
+        path.url_id
+    )
+    .fetch_optional(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?
+    .ok_or(CustomError::HttpError(CustomHttpError::RevisionNotFound))?;
+
+    // The old values went through these checks once, but the rules may have changed since.
+    if revision.short_url != current.short_url {
+        validate_short_code(
+            &data.secrets,
+            &revision.short_url,
+            auth_guard.acts_as_admin(),
+        )?;
+        if short_url_taken_ignoring_case(
+            &data,
+            current.domain_id,
+            &revision.short_url,
+            Some(path.url_id),
+        )
+        .await?
+        {
+            return Err(CustomError::HttpError(
+                CustomHttpError::ShortUrlAlreadyExists,
+            ));
+        }
+    }
+
+    ensure_url_is_safe(data.url_safety.as_ref(), &revision.original_url).await?;
+
+    let origin = LinkKey {
+        domain_id: current.domain_id,
+        short_url: revision.short_url.clone(),
+    };
+    check_redirect_chain(&data.db, &data.secrets, &origin, &revision.original_url).await?;
+
+    if let Some(collection_id) = revision.collection_id {
+        ensure_collection_owned(&data.db, auth_guard.user.id, collection_id).await?;
+    }
+
+    let mut tx = data.db.begin().await.map_err(CustomError::DataBaseError)?;
+
+    sqlx::query!(
+        r#"
+        UPDATE urls
+        SET original_url = $1, short_url = $2, slug = $3, category = $4, title = $5,
+            notes = $6, is_favorite = $7, collection_id = $8,
+            flagged_at = CASE WHEN original_url = $1 THEN flagged_at END,
+            flag_reason = CASE WHEN original_url = $1 THEN flag_reason END
+        WHERE id = $9
+        "#,
+        revision.original_url,
+        revision.short_url,
+        slugify(&revision.short_url),
+        revision.category.to_string(),
+        revision.title,
+        revision.notes,
+        revision.is_favorite,
+        revision.collection_id,
+        path.url_id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|err| match err {
+        sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+            CustomError::HttpError(CustomHttpError::ShortUrlAlreadyExists)
+        }
+        err => CustomError::DataBaseError(err),
+    })?;
+
+    record_revision(&mut *tx, path.url_id, auth_guard.user.id).await?;
+
+    tx.commit().await.map_err(CustomError::DataBaseError)?;
+
+    if revision.original_url != current.original_url {
+        spawn_metadata_refresh(
+            data.db.clone(),
+            data.page_fetcher.clone(),
+            path.url_id,
+            revision.original_url.clone(),
+        );
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "message": "URL rolled back successfully"
+    })))
+}
@@ -0,0 +1,317 @@
+use actix_web::{post, web, HttpRequest, HttpResponse};
+
+use argon2::{
+    password_hash::{PasswordHash, PasswordVerifier},
+    Argon2,
+};
+
+use sqlx::PgPool;
+
+use tracing::info;
+
+use crate::models::two_factor::{ConfirmTwoFactorSchema, DisableTwoFactorSchema, MfaLoginSchema};
+
+use crate::models::url::QrFormat;
+
+use crate::models::user::User;
+
+use super::auth::issue_tokens;
+
+use crate::app_state::AppState;
+
+use crate::jwt_auth::JwtMiddleware;
+
+use crate::scope::Scope;
+
+use crate::login_throttle::{locked_out_for, record_failure, record_success, ThrottleKey};
+
+use crate::token::session::ClientInfo;
+
+use crate::token::action_token::{verify_action_token, TokenPurpose};
+
+use crate::totp::{
+    build_totp, generate_recovery_codes, generate_secret, hash_recovery_code, is_totp_code,
+    verify_code,
+};
+
+use crate::utils::qr::{render_qr, QrOptions};
+
+use crate::custom_error::{CustomError, CustomHttpError};
+
+/// Accepts a TOTP code, or burns one of the recovery codes.
+pub(crate) async fn check_second_factor(
+    db: &PgPool,
+    issuer: &str,
+    user: &User,
+    code: &str,
+) -> Result<(), CustomError> {
+    let invalid = || CustomError::HttpError(CustomHttpError::TwoFactorCodeInvalid);
+
+    if is_totp_code(code) {
+        let secret = user.totp_secret.as_deref().ok_or_else(invalid)?;
+        let totp = build_totp(secret, issuer, &user.email)?;
+        let step = verify_code(&totp, code, user.totp_last_used_step).ok_or_else(invalid)?;
+
+        // Guarded again here, two requests may race with the same code.
+        let accepted = sqlx::query!(
+            r#"
+            UPDATE users SET totp_last_used_step = $1
+            WHERE id = $2 AND (totp_last_used_step IS NULL OR totp_last_used_step < $1)
+            "#,
+            step,
+            user.id
+        )
+        .execute(db)
+        .await
+        .map_err(CustomError::DataBaseError)?
+        .rows_affected();
+
+        return if accepted == 1 {
+            Ok(())
+        } else {
+            Err(invalid())
+        };
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE recovery_codes SET used_at = now()
+        WHERE id = (
+            SELECT id FROM recovery_codes
+            WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+            LIMIT 1
+        )
+        RETURNING id
+        "#,
+        user.id,
+        hash_recovery_code(code)
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(CustomError::DataBaseError)?
+    .map(|_| ())
+    .ok_or_else(invalid)
+}
+
+/// Starts enrollment with a new secret, 2FA is only enabled once a first code confirms it.
+#[post("/auth/2fa/enroll")]
+pub async fn enroll_two_factor(
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::AccountAdmin)?;
+
+    if auth_guard.user.totp_enabled_at.is_some() {
+        return Err(CustomError::HttpError(
+            CustomHttpError::TwoFactorAlreadyEnabled,
+        ));
+    }
+
+    let secret = generate_secret();
+    let totp = build_totp(&secret, &data.secrets.domain, &auth_guard.user.email)?;
+    let otpauth_uri = totp.get_url();
+
+    let qr_code = render_qr(
+        &otpauth_uri,
+        &QrOptions {
+            format: QrFormat::Svg,
+            ..QrOptions::default()
+        },
+    )?;
+
+    sqlx::query!(
+        r#"UPDATE users SET totp_secret = $1, totp_last_used_step = NULL, updated_at = now() WHERE id = $2"#,
+        secret,
+        auth_guard.user.id
+    )
+    .execute(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "data": {
+            "secret": secret,
+            "otpauth_uri": otpauth_uri,
+            "qr_code_svg": String::from_utf8_lossy(&qr_code)
+        }
+    })))
+}
+
+/// Enables 2FA and hands out the recovery codes, the only time they are shown.
+#[post("/auth/2fa/confirm")]
+pub async fn confirm_two_factor(
+    body: web::Json<ConfirmTwoFactorSchema>,
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::AccountAdmin)?;
+
+    let user = &auth_guard.user;
+    if user.totp_enabled_at.is_some() {
+        return Err(CustomError::HttpError(
+            CustomHttpError::TwoFactorAlreadyEnabled,
+        ));
+    }
+    let secret = user.totp_secret.as_deref().ok_or(CustomError::HttpError(
+        CustomHttpError::TwoFactorNotEnrolled,
+    ))?;
+
+    let totp = build_totp(secret, &data.secrets.domain, &user.email)?;
+    let step = verify_code(&totp, &body.code, None).ok_or(CustomError::HttpError(
+        CustomHttpError::TwoFactorCodeInvalid,
+    ))?;
+
+    let recovery_codes = generate_recovery_codes();
+    let code_hashes: Vec<String> = recovery_codes
+        .iter()
+        .map(|code| hash_recovery_code(code))
+        .collect();
+
+    let mut tx = data.db.begin().await.map_err(CustomError::DataBaseError)?;
+
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET totp_enabled_at = now(), totp_last_used_step = $1, updated_at = now()
+        WHERE id = $2
+        "#,
+        step,
+        user.id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    sqlx::query!(r#"DELETE FROM recovery_codes WHERE user_id = $1"#, user.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(CustomError::DataBaseError)?;
+
+    sqlx::query!(
+        r#"INSERT INTO recovery_codes (user_id, code_hash) SELECT $1, UNNEST($2::VARCHAR[])"#,
+        user.id,
+        &code_hashes
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    tx.commit().await.map_err(CustomError::DataBaseError)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "message": "Two-factor authentication enabled, keep the recovery codes somewhere safe",
+        "recovery_codes": recovery_codes
+    })))
+}
+
+#[post("/auth/2fa/disable")]
+pub async fn disable_two_factor(
+    body: web::Json<DisableTwoFactorSchema>,
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::AccountAdmin)?;
+
+    let user = &auth_guard.user;
+    if user.totp_enabled_at.is_none() {
+        return Err(CustomError::HttpError(CustomHttpError::TwoFactorNotEnabled));
+    }
+
+    PasswordHash::new(&user.password)
+        .and_then(|parsed_hash| {
+            Argon2::default().verify_password(body.password.as_bytes(), &parsed_hash)
+        })
+        .map_err(|_| CustomError::HttpError(CustomHttpError::CredentialsNotCorrect))?;
+
+    check_second_factor(&data.db, &data.secrets.domain, user, &body.code).await?;
+
+    let mut tx = data.db.begin().await.map_err(CustomError::DataBaseError)?;
+
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET totp_secret = NULL, totp_enabled_at = NULL, totp_last_used_step = NULL, updated_at = now()
+        WHERE id = $1
+        "#,
+        user.id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    sqlx::query!(r#"DELETE FROM recovery_codes WHERE user_id = $1"#, user.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(CustomError::DataBaseError)?;
+
+    tx.commit().await.map_err(CustomError::DataBaseError)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "message": "Two-factor authentication disabled"
+    })))
+}
+
+/// Second step of a login with 2FA, takes the `mfa_token` returned by `login`.
+#[post("/auth/login/mfa")]
+pub async fn login_mfa(
+    req: HttpRequest,
+    body: web::Json<MfaLoginSchema>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let claims = verify_action_token(
+        &data.secrets.access_token_public_key,
+        &body.mfa_token,
+        TokenPurpose::MfaChallenge,
+    )
+    .map_err(|_| CustomError::HttpError(CustomHttpError::MfaTokenInvalid))?;
+
+    let user = sqlx::query_as!(
+        User,
+        r#"SELECT * FROM users WHERE id = $1 AND email = $2 AND totp_enabled_at IS NOT NULL"#,
+        claims.sub,
+        claims.email
+    )
+    .fetch_optional(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?
+    .ok_or(CustomError::HttpError(CustomHttpError::MfaTokenInvalid))?;
+
+    // Codes are guessed faster than passwords, they share the login lockout.
+    let ip = ClientInfo::from_request(&req, &data.secrets)
+        .ip
+        .unwrap_or_default();
+    let throttle_keys = [ThrottleKey::Ip(&ip), ThrottleKey::Account(&user.email)];
+
+    let mut redis_client = data
+        .redis_client
+        .get_async_connection()
+        .await
+        .map_err(CustomError::RedisError)?;
+
+    if let Some(retry_after) = locked_out_for(&mut redis_client, &throttle_keys)
+        .await
+        .map_err(CustomError::RedisError)?
+    {
+        return Err(CustomError::HttpError(
+            CustomHttpError::TooManyLoginAttempts(retry_after),
+        ));
+    }
+
+    if let Err(err) = check_second_factor(&data.db, &data.secrets.domain, &user, &body.code).await {
+        info!(target: "security", email = %user.email, %ip, outcome = "mfa_failure", "Login attempt");
+        record_failure(&mut redis_client, &data.secrets, &throttle_keys)
+            .await
+            .map_err(CustomError::RedisError)?;
+        return Err(err);
+    }
+
+    info!(target: "security", email = %user.email, %ip, outcome = "mfa_success", "Login attempt");
+    record_success(&mut redis_client, &user.email)
+        .await
+        .map_err(CustomError::RedisError)?;
+
+    issue_tokens(&req, &data, &user).await
+}
@@ -1,17 +1,40 @@
-use actix_web::{delete, get, http, patch, post, web, HttpResponse};
+use actix_web::{delete, get, http, patch, post, web, HttpRequest, HttpResponse};
 
 use validator::Validate;
 
+use tracing::warn;
+
 use crate::models::url::{
-    CreateUrl, OriginalUrl, UpdateUrl, Url, UrlPath, UrlPathRedirect, UrlQuery, UrlRecord,
+    CreateUrl, OriginalUrl, QrQuery, UpdateUrl, Url, UrlPath, UrlPathRedirect, UrlQuery, UrlRecord,
+    SHORT_URL_REGEX,
 };
 
+use crate::models::domain::{normalize_host, Domain};
+
 use crate::app_state::AppState;
 
 use crate::jwt_auth::JwtMiddleware;
 
+use crate::scope::Scope;
+
 use crate::custom_error::{handle_validation_error, CustomError, CustomHttpError};
 
+use crate::api::collection::ensure_collection_owned;
+
+use crate::api::revision::record_revision;
+
+use crate::link_metadata::{refresh_link_metadata, spawn_metadata_refresh};
+
+use crate::redirect_chain::{check_redirect_chain, LinkKey};
+
+use crate::short_url_misses::{record_short_url_miss, MISS_BUDGET};
+
+use crate::url_safety::{ensure_url_is_safe, UrlVerdict};
+
+use crate::utils::qr::{render_qr, QrOptions};
+
+use crate::utils::short_code::validate_short_code;
+
 use crate::utils::slugify::slugify;
 
 #[post("/url")]
@@ -20,36 +43,94 @@ pub async fn create_url(
     data: web::Data<AppState>,
     auth_guard: JwtMiddleware,
 ) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::LinksWrite)?;
+    auth_guard.require_verified_email()?;
+
     let is_valid = body.validate();
 
     if let Err(validation_error) = is_valid {
         return handle_validation_error(validation_error);
     }
 
+    validate_short_code(&data.secrets, &body.short_url, auth_guard.acts_as_admin())?;
+
+    if let Some(domain_id) = body.domain_id {
+        let domain = sqlx::query!(
+            r#"SELECT verified_at FROM domains WHERE id = $1 AND user_id = $2"#,
+            domain_id,
+            auth_guard.user.id
+        )
+        .fetch_optional(&data.db)
+        .await
+        .map_err(CustomError::DataBaseError)?
+        .ok_or(CustomError::HttpError(CustomHttpError::DomainNotFound))?;
+
+        if domain.verified_at.is_none() {
+            return Err(CustomError::HttpError(CustomHttpError::DomainNotVerified));
+        }
+    }
+
+    if let Some(collection_id) = body.collection_id {
+        ensure_collection_owned(&data.db, auth_guard.user.id, collection_id).await?;
+    }
+
+    ensure_url_is_safe(data.url_safety.as_ref(), &body.original_url).await?;
+
+    let origin = LinkKey {
+        domain_id: body.domain_id,
+        short_url: body.short_url.to_string(),
+    };
+    check_redirect_chain(&data.db, &data.secrets, &origin, &body.original_url).await?;
+
+    if short_url_taken_ignoring_case(&data, body.domain_id, &body.short_url, None).await? {
+        return Err(CustomError::HttpError(
+            CustomHttpError::ShortUrlAlreadyExists,
+        ));
+    }
+
     let new_url: Url = match sqlx::query_as!(
         Url,
         r#"
-        INSERT INTO urls (original_url, short_url, user_id, views, category, slug)
-        VALUES ($1, $2, $3, $4, $5, $6)
+        INSERT INTO urls (original_url, short_url, user_id, domain_id, views, category, slug, title, notes, is_favorite, collection_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, NULLIF($8, ''), NULLIF($9, ''), $10, $11)
         RETURNING *
         "#,
         body.original_url.to_string(),
         body.short_url.to_string(),
         auth_guard.user.id,
+        body.domain_id,
         0,
         body.category.to_string().into(),
-        slugify(&body.short_url)
+        slugify(&body.short_url),
+        body.title,
+        body.notes,
+        body.is_favorite.unwrap_or(false),
+        body.collection_id
     )
     .fetch_one(&data.db)
     .await
     {
         Ok(url) => url,
+        Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
+            return Err(CustomError::HttpError(
+                CustomHttpError::ShortUrlAlreadyExists,
+            ));
+        }
         Err(e) => {
             println!("Error creating URL: {:?}", e);
             return Err(CustomError::DataBaseError(e));
         }
     };
 
+    record_revision(&data.db, new_url.id, auth_guard.user.id).await?;
+
+    spawn_metadata_refresh(
+        data.db.clone(),
+        data.page_fetcher.clone(),
+        new_url.id,
+        new_url.original_url.clone(),
+    );
+
     Ok(HttpResponse::Created().json(serde_json::json!({
         "status": "success",
         "data": new_url
@@ -62,6 +143,8 @@ pub async fn get_all_url_record(
     query: web::Query<UrlQuery>,
     auth_guard: JwtMiddleware,
 ) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::LinksRead)?;
+
     let limit = query.limit.unwrap_or(5);
     let offset = query.offset.unwrap_or(0);
 
@@ -70,105 +153,237 @@ pub async fn get_all_url_record(
             sqlx::query_as!(
                 Url,
                 r#"
-                SELECT id, original_url, short_url, user_id, views, category, slug, created_at, updated_at
+                SELECT id, original_url, short_url, user_id, domain_id, collection_id, views, category, slug, title, notes, is_favorite, flagged_at, flag_reason, meta_title, meta_description, meta_image_url, meta_favicon_url, metadata_fetched_at, health_status_code, health_latency_ms, health_error, health_checked_at, health_failures, case_insensitive, created_at, updated_at
                 FROM urls
                 WHERE user_id = $1
                 AND (urls.category = $2 OR $2 = 'All')
+                AND ($5::BOOLEAN IS NULL OR is_favorite = $5)
+                AND ($6::UUID IS NULL OR collection_id = $6)
                 ORDER BY created_at DESC
                 LIMIT $3 OFFSET $4
                 "#,
                 auth_guard.user.id,
                 category.to_string(),
                 limit,
-                offset
+                offset,
+                query.favorite,
+                query.collection_id
             )
             .fetch_all(&data.db)
             .await
-            .map_err(|err| CustomError::DataBaseError(err))?
+            .map_err(CustomError::DataBaseError)?
         },
         None => {
             sqlx::query_as!(
                 Url,
                 r#"
-                SELECT id, original_url, short_url, user_id, views, category, slug, created_at, updated_at
+                SELECT id, original_url, short_url, user_id, domain_id, collection_id, views, category, slug, title, notes, is_favorite, flagged_at, flag_reason, meta_title, meta_description, meta_image_url, meta_favicon_url, metadata_fetched_at, health_status_code, health_latency_ms, health_error, health_checked_at, health_failures, case_insensitive, created_at, updated_at
                 FROM urls
                 WHERE user_id = $1
+                AND ($4::BOOLEAN IS NULL OR is_favorite = $4)
+                AND ($5::UUID IS NULL OR collection_id = $5)
                 ORDER BY created_at DESC
                 LIMIT $2 OFFSET $3
                 "#,
                 auth_guard.user.id,
                 limit,
-                offset
+                offset,
+                query.favorite,
+                query.collection_id
             )
             .fetch_all(&data.db)
             .await
-            .map_err(|err| CustomError::DataBaseError(err))?
+            .map_err(CustomError::DataBaseError)?
         }
     };
 
     let url_records: Vec<UrlRecord> = records
         .into_iter()
-        .map(|record| UrlRecord {
-            user_id: auth_guard.user.id,
-            id: record.id,
-            original_url: record.original_url,
-            short_url: record.short_url.clone(),
-            views: record.views,
-            category: record.category.clone(),
-            slug: slugify(&record.short_url),
-            created_at: record.created_at,
-            updated_at: record.updated_at,
-        })
+        .map(|record| UrlRecord::new(auth_guard.user.id, record))
         .collect();
 
     Ok(HttpResponse::Ok().json(serde_json::json!({"status": "success", "data": url_records})))
 }
 
+#[get("/url/broken")]
+pub async fn get_broken_urls(
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::StatsRead)?;
+
+    let records = sqlx::query_as!(
+        Url,
+        r#"
+        SELECT * FROM urls
+        WHERE user_id = $1 AND health_failures >= $2
+        ORDER BY health_failures DESC, health_checked_at DESC
+        "#,
+        auth_guard.user.id,
+        data.secrets.health_check_failure_threshold
+    )
+    .fetch_all(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    let url_records: Vec<UrlRecord> = records
+        .into_iter()
+        .map(|record| UrlRecord::new(auth_guard.user.id, record))
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "results": url_records.len(),
+        "data": url_records
+    })))
+}
+
 #[patch("/url/{url_id}")]
 pub async fn update_url(
     data: web::Data<AppState>,
     body: web::Json<UpdateUrl>,
-    _auth_guard: JwtMiddleware,
+    auth_guard: JwtMiddleware,
     path: web::Path<UrlPath>,
 ) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::LinksWrite)?;
+
     let is_valid = body.validate();
 
     if let Err(validation_error) = is_valid {
         return handle_validation_error(validation_error);
     }
 
-    let update_result = if let Some(original_url) = &body.original_url {
-        sqlx::query!(
-            r#"UPDATE urls SET original_url = $1 WHERE id = $2"#,
+    if let Some(short_url) = &body.short_url {
+        validate_short_code(&data.secrets, short_url, auth_guard.acts_as_admin())?;
+    }
+
+    let current = sqlx::query!(
+        r#"SELECT domain_id, short_url, original_url FROM urls WHERE id = $1 AND user_id = $2"#,
+        path.url_id,
+        auth_guard.user.id
+    )
+    .fetch_optional(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?
+    .ok_or(CustomError::HttpError(CustomHttpError::UrlNotFound))?;
+
+    if let Some(original_url) = &body.original_url {
+        ensure_url_is_safe(data.url_safety.as_ref(), original_url).await?;
+    }
+
+    if body.original_url.is_some() || body.short_url.is_some() {
+        let origin = LinkKey {
+            domain_id: current.domain_id,
+            short_url: body.short_url.clone().unwrap_or(current.short_url),
+        };
+        let destination = body.original_url.as_ref().unwrap_or(&current.original_url);
+        check_redirect_chain(&data.db, &data.secrets, &origin, destination).await?;
+
+        if let Some(short_url) = &body.short_url {
+            if short_url_taken_ignoring_case(&data, current.domain_id, short_url, Some(path.url_id))
+                .await?
+            {
+                return Err(CustomError::HttpError(
+                    CustomHttpError::ShortUrlAlreadyExists,
+                ));
+            }
+        }
+    }
+
+    if let Some(Some(collection_id)) = body.collection_id {
+        ensure_collection_owned(&data.db, auth_guard.user.id, collection_id).await?;
+    }
+
+    let has_details = body.title.is_some()
+        || body.notes.is_some()
+        || body.is_favorite.is_some()
+        || body.collection_id.is_some();
+
+    let update_query = if let Some(original_url) = &body.original_url {
+        Some(sqlx::query!(
+            r#"UPDATE urls SET original_url = $1, flagged_at = NULL, flag_reason = NULL WHERE id = $2 AND user_id = $3"#,
             original_url,
-            path.url_id.clone()
-        )
+            path.url_id.clone(),
+            auth_guard.user.id
+        ))
     } else if let Some(short_url) = &body.short_url {
-        sqlx::query!(
-            r#"UPDATE urls SET short_url = $1, slug = $2 WHERE id = $3"#,
+        Some(sqlx::query!(
+            r#"UPDATE urls SET short_url = $1, slug = $2 WHERE id = $3 AND user_id = $4"#,
             short_url,
             slugify(&short_url),
-            path.url_id.clone()
-        )
+            path.url_id.clone(),
+            auth_guard.user.id
+        ))
     } else if let Some(category) = &body.category {
-        sqlx::query!(
-            r#"UPDATE urls SET category = $1 WHERE id = $2"#,
+        Some(sqlx::query!(
+            r#"UPDATE urls SET category = $1 WHERE id = $2 AND user_id = $3"#,
             category.to_string(),
-            path.url_id.clone()
-        )
+            path.url_id.clone(),
+            auth_guard.user.id
+        ))
+    } else if has_details {
+        None
     } else {
         return Err(CustomError::OtherError(
             "Something happend updating the fields".to_string(),
         ));
+    };
+
+    // Both updates land together or not at all.
+    let mut tx = data.db.begin().await.map_err(CustomError::DataBaseError)?;
+
+    // Title, notes, favorite and collection are independent of the fields above and can
+    // be sent together, an empty string clears the title or the notes.
+    if has_details {
+        sqlx::query!(
+            r#"
+            UPDATE urls
+            SET title = CASE WHEN $1::TEXT IS NULL THEN title ELSE NULLIF($1, '') END,
+                notes = CASE WHEN $2::TEXT IS NULL THEN notes ELSE NULLIF($2, '') END,
+                is_favorite = COALESCE($3, is_favorite),
+                collection_id = CASE WHEN $4 THEN $5 ELSE collection_id END
+            WHERE id = $6 AND user_id = $7
+            "#,
+            body.title,
+            body.notes,
+            body.is_favorite,
+            body.collection_id.is_some(),
+            body.collection_id.flatten(),
+            path.url_id,
+            auth_guard.user.id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(CustomError::DataBaseError)?;
+    }
+
+    let update_result = match update_query {
+        Some(query) => query.execute(&mut *tx).await.map(|_| ()),
+        None => Ok(()),
+    };
+
+    if update_result.is_ok() {
+        record_revision(&mut *tx, path.url_id, auth_guard.user.id).await?;
+        tx.commit().await.map_err(CustomError::DataBaseError)?;
+    }
+
+    if let (Ok(_), Some(original_url)) = (&update_result, &body.original_url) {
+        spawn_metadata_refresh(
+            data.db.clone(),
+            data.page_fetcher.clone(),
+            path.url_id,
+            original_url.clone(),
+        );
     }
-    .execute(&data.db)
-    .await;
 
     match update_result {
         Ok(_) => Ok(HttpResponse::Ok().json(serde_json::json!({
             "status": "success",
             "message": "URL updated successfully"
         }))),
+        Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => Err(
+            CustomError::HttpError(CustomHttpError::ShortUrlAlreadyExists),
+        ),
         Err(e) => {
             println!("Error updating URL: {:?}", e);
             Err(CustomError::DataBaseError(e))
@@ -180,8 +395,10 @@ pub async fn update_url(
 pub async fn delete_url(
     path: web::Path<UrlPath>,
     data: web::Data<AppState>,
-    _auth_guard: JwtMiddleware,
+    auth_guard: JwtMiddleware,
 ) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::LinksWrite)?;
+
     let delete_result = sqlx::query!(r#"DELETE FROM "urls" WHERE id = $1"#, path.url_id.clone())
         .execute(&data.db)
         .await;
@@ -199,8 +416,10 @@ pub async fn delete_url(
 pub async fn get_url_by_id(
     path: web::Path<UrlPath>,
     data: web::Data<AppState>,
-    _auth_guard: JwtMiddleware,
+    auth_guard: JwtMiddleware,
 ) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::LinksRead)?;
+
     let url_response = match sqlx::query_as!(
         Url,
         r#"SELECT * FROM urls WHERE id = $1"#,
@@ -223,28 +442,284 @@ pub async fn get_url_by_id(
 
 #[get("/url/redirect/{short_url}")]
 pub async fn redirect_to_original_url(
+    req: HttpRequest,
     data: web::Data<AppState>,
     path: web::Path<UrlPathRedirect>,
 ) -> Result<HttpResponse, CustomError> {
-    let original_url = match sqlx::query_as!(
+    // Exact matches win over case-insensitive ones in case colliding codes still exist.
+    let target = sqlx::query_as!(
         OriginalUrl,
         r#"UPDATE urls
     SET views = views + 1
-    WHERE short_url = $1
-    RETURNING original_url"#,
-        path.short_url.clone()
+    WHERE id = (
+        SELECT id FROM urls
+        WHERE domain_id IS NULL
+        AND (short_url = $1 OR ($2 AND lower(short_url) = lower($1)))
+        ORDER BY short_url = $1 DESC
+        LIMIT 1
     )
-    .fetch_one(&data.db)
+    RETURNING id, original_url, flag_reason"#,
+        path.short_url.clone(),
+        data.secrets.case_insensitive_short_urls
+    )
+    .fetch_optional(&data.db)
     .await
-    {
-        Ok(row) => row.original_url,
-        Err(e) => {
-            println!("Error fetching URL: {:?}", e);
-            return Err(CustomError::DataBaseError(e));
+    .map_err(CustomError::DataBaseError)?;
+
+    match target {
+        Some(target) => redirect_response(&data, target).await,
+        None => not_found_response(&req, &data, None, &path.short_url).await,
+    }
+}
+
+const DEFAULT_NOT_FOUND_PAGE: &str = concat!(
+    "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Link not found</title></head>",
+    "<body><h1>Link not found</h1>",
+    "<p>This short link does not exist or has been removed.</p></body></html>"
+);
+
+/// Records the miss, then answers API clients with the usual JSON 404 and browsers
+/// with the configured fallback redirect or not found page. Only codes that could be
+/// created are recorded, the rest is scanners and typos in other paths, and only as
+/// many per second as `MISS_BUDGET` allows.
+async fn not_found_response(
+    req: &HttpRequest,
+    data: &AppState,
+    host: Option<&str>,
+    short_url: &str,
+) -> Result<HttpResponse, CustomError> {
+    // Best effort, failing to count a miss must not turn the 404 into an error.
+    if SHORT_URL_REGEX.is_match(short_url) && MISS_BUDGET.try_take() {
+        if let Err(err) = record_short_url_miss(&data.db, host, short_url).await {
+            warn!("Failed to record short URL miss: {}", err);
         }
-    };
+    }
+
+    let wants_html = req
+        .headers()
+        .get(http::header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("text/html"));
+
+    if !wants_html {
+        return Err(CustomError::HttpError(CustomHttpError::UrlNotFound));
+    }
+
+    if let Some(fallback) = &data.secrets.not_found_redirect_url {
+        return Ok(HttpResponse::Found()
+            .append_header((http::header::LOCATION, fallback.clone()))
+            .finish());
+    }
+
+    let page = data
+        .secrets
+        .not_found_page
+        .clone()
+        .unwrap_or_else(|| DEFAULT_NOT_FOUND_PAGE.to_string());
+
+    Ok(HttpResponse::NotFound()
+        .content_type(http::header::ContentType::html())
+        .body(page))
+}
+
+/// Builds the redirect to the destination, refusing it when redirect-time safety
+/// checks are enabled and the link is flagged or now matches the blocklist.
+async fn redirect_response(
+    data: &AppState,
+    target: OriginalUrl,
+) -> Result<HttpResponse, CustomError> {
+    if data.secrets.check_url_safety_on_redirect {
+        if let Some(reason) = target.flag_reason {
+            return Err(CustomError::HttpError(CustomHttpError::UnsafeUrl(reason)));
+        }
+
+        if let UrlVerdict::Blocked(reason) = data.url_safety.check(&target.original_url).await? {
+            sqlx::query!(
+                r#"UPDATE urls SET flagged_at = now(), flag_reason = $1 WHERE id = $2"#,
+                reason,
+                target.id
+            )
+            .execute(&data.db)
+            .await
+            .map_err(CustomError::DataBaseError)?;
+
+            return Err(CustomError::HttpError(CustomHttpError::UnsafeUrl(reason)));
+        }
+    }
 
     Ok(HttpResponse::Found()
-        .append_header((http::header::LOCATION, original_url.clone()))
+        .append_header((http::header::LOCATION, target.original_url))
         .finish())
 }
+
+#[post("/url/{url_id}/metadata/refresh")]
+pub async fn refresh_url_metadata(
+    path: web::Path<UrlPath>,
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::LinksWrite)?;
+
+    let url = sqlx::query!(
+        r#"SELECT original_url FROM urls WHERE id = $1 AND user_id = $2"#,
+        path.url_id,
+        auth_guard.user.id
+    )
+    .fetch_optional(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?
+    .ok_or(CustomError::HttpError(CustomHttpError::UrlNotFound))?;
+
+    refresh_link_metadata(
+        &data.db,
+        data.page_fetcher.as_ref(),
+        path.url_id,
+        &url.original_url,
+    )
+    .await?;
+
+    let url = sqlx::query_as!(Url, r#"SELECT * FROM urls WHERE id = $1"#, path.url_id)
+        .fetch_one(&data.db)
+        .await
+        .map_err(CustomError::DataBaseError)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({"status": "success", "data": url})))
+}
+
+#[get("/url/{url_id}/qr")]
+pub async fn get_url_qr_code(
+    req: HttpRequest,
+    path: web::Path<UrlPath>,
+    query: web::Query<QrQuery>,
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::LinksRead)?;
+
+    if let Err(validation_error) = query.validate() {
+        return handle_validation_error(validation_error);
+    }
+
+    let url = sqlx::query_as!(
+        Url,
+        r#"SELECT * FROM urls WHERE id = $1 AND user_id = $2"#,
+        path.url_id,
+        auth_guard.user.id
+    )
+    .fetch_optional(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?
+    .ok_or(CustomError::HttpError(CustomHttpError::UrlNotFound))?;
+
+    let short_link = match url.domain_id {
+        Some(domain_id) => {
+            sqlx::query_as!(Domain, r#"SELECT * FROM domains WHERE id = $1"#, domain_id)
+                .fetch_one(&data.db)
+                .await
+                .map_err(CustomError::DataBaseError)?
+                .short_link(&url.short_url)
+        }
+        None => data.secrets.short_link(&url.short_url),
+    };
+    let options = QrOptions::from_query(&query);
+    let etag = options.etag(&short_link);
+
+    let not_modified = req
+        .headers()
+        .get(http::header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .any(|tag| tag.trim() == etag || tag.trim() == "*")
+        });
+
+    if not_modified {
+        return Ok(HttpResponse::NotModified()
+            .insert_header((http::header::ETAG, etag))
+            .finish());
+    }
+
+    let image = render_qr(&short_link, &options)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(options.content_type())
+        .insert_header((http::header::ETAG, etag))
+        .insert_header((http::header::CACHE_CONTROL, "private, max-age=86400"))
+        .body(image))
+}
+
+/// Redirect served at the root of branded domains, e.g. `go.acme.com/launch`.
+/// Hosts that are not a verified domain fall back to the shared namespace.
+#[get("/{short_url}")]
+pub async fn redirect_by_host(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<UrlPathRedirect>,
+) -> Result<HttpResponse, CustomError> {
+    let host = normalize_host(req.connection_info().host());
+
+    let target = sqlx::query_as!(
+        OriginalUrl,
+        r#"UPDATE urls
+    SET views = views + 1
+    WHERE id = (
+        SELECT urls.id FROM urls
+        LEFT JOIN domains ON domains.id = urls.domain_id
+        WHERE urls.domain_id IS NOT DISTINCT FROM (
+            SELECT id FROM domains WHERE hostname = $2 AND verified_at IS NOT NULL
+        )
+        AND (
+            urls.short_url = $1
+            OR (($3 OR COALESCE(domains.case_insensitive, FALSE))
+                AND lower(urls.short_url) = lower($1))
+        )
+        ORDER BY urls.short_url = $1 DESC
+        LIMIT 1
+    )
+    RETURNING id, original_url, flag_reason"#,
+        path.short_url.clone(),
+        host,
+        data.secrets.case_insensitive_short_urls
+    )
+    .fetch_optional(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    match target {
+        Some(target) => redirect_response(&data, target).await,
+        None => not_found_response(&req, &data, Some(&host), &path.short_url).await,
+    }
+}
+
+/// With case-insensitive lookups on for the namespace, `Launch` and `launch` would reach
+/// the same link, so a code is taken as soon as another link matches it ignoring case.
+/// The unique indexes enforce the same, this check answers with a clear error first.
+pub async fn short_url_taken_ignoring_case(
+    data: &AppState,
+    domain_id: Option<uuid::Uuid>,
+    short_url: &str,
+    except_url_id: Option<uuid::Uuid>,
+) -> Result<bool, CustomError> {
+    let taken = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM urls
+            WHERE domain_id IS NOT DISTINCT FROM $1
+            AND lower(short_url) = lower($2)
+            AND id IS DISTINCT FROM $3
+        )
+        AND ($4 OR COALESCE((SELECT case_insensitive FROM domains WHERE id = $1), FALSE))
+        AS "taken!"
+        "#,
+        domain_id,
+        short_url,
+        except_url_id,
+        data.secrets.case_insensitive_short_urls
+    )
+    .fetch_one(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    Ok(taken)
+}
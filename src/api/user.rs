@@ -0,0 +1,178 @@
+use actix_web::{delete, patch, post, web, HttpResponse};
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+
+use tracing::warn;
+
+use validator::Validate;
+
+use crate::models::user::{ChangePasswordSchema, DeleteAccountSchema, UpdateUserSchema, User};
+
+use super::auth::send_verification_email;
+
+use super::reponse::{filter_user_record, UserResponse};
+
+use crate::app_state::AppState;
+
+use crate::jwt_auth::JwtMiddleware;
+
+use crate::scope::Scope;
+
+use crate::token::session::end_all_sessions;
+
+use crate::custom_error::{handle_validation_error, CustomError, CustomHttpError};
+
+fn ensure_password(user: &User, password: &str) -> Result<(), CustomError> {
+    PasswordHash::new(&user.password)
+        .and_then(|parsed_hash| {
+            Argon2::default().verify_password(password.as_bytes(), &parsed_hash)
+        })
+        .map_err(|_| CustomError::HttpError(CustomHttpError::CredentialsNotCorrect))
+}
+
+async fn log_out_everywhere(data: &AppState, user: &User) -> Result<(), CustomError> {
+    let mut redis_client = data
+        .redis_client
+        .get_async_connection()
+        .await
+        .map_err(CustomError::RedisError)?;
+
+    end_all_sessions(&mut redis_client, user.id)
+        .await
+        .map_err(CustomError::RedisError)?;
+
+    Ok(())
+}
+
+/// A new email address has to be verified again.
+#[patch("/users/me")]
+pub async fn update_me(
+    body: web::Json<UpdateUserSchema>,
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::AccountAdmin)?;
+
+    if let Err(validation_error) = body.validate() {
+        return handle_validation_error(validation_error);
+    }
+
+    let email = body
+        .email
+        .as_deref()
+        .map(|email| email.trim().to_lowercase())
+        .filter(|email| *email != auth_guard.user.email);
+
+    let user = sqlx::query_as!(
+        User,
+        r#"
+        UPDATE users
+        SET name = COALESCE($1, name),
+            email = COALESCE($2, email),
+            email_verified_at = CASE WHEN $2::TEXT IS NULL THEN email_verified_at END,
+            updated_at = now()
+        WHERE id = $3
+        RETURNING *
+        "#,
+        body.name.as_deref().map(str::trim),
+        email,
+        auth_guard.user.id
+    )
+    .fetch_one(&data.db)
+    .await
+    .map_err(|err| match err {
+        sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+            CustomError::HttpError(CustomHttpError::UserAlreadyExists)
+        }
+        err => CustomError::DataBaseError(err),
+    })?;
+
+    if email.is_some() {
+        if let Err(err) = send_verification_email(&data, &user).await {
+            warn!("Failed to send the verification email: {}", err);
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(UserResponse {
+        status: "success".to_string(),
+        data: filter_user_record(&user),
+    }))
+}
+
+/// Every session is ended, the new password has to be used to login again.
+#[post("/users/me/password")]
+pub async fn change_password(
+    body: web::Json<ChangePasswordSchema>,
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::AccountAdmin)?;
+
+    if let Err(validation_error) = body.validate() {
+        return handle_validation_error(validation_error);
+    }
+
+    ensure_password(&auth_guard.user, &body.current_password)?;
+
+    let salt = SaltString::generate(&mut OsRng);
+    let hash_pass = Argon2::default()
+        .hash_password(body.new_password.as_bytes(), &salt)
+        .map_err(|err| CustomError::OtherError(err.to_string()))?
+        .to_string();
+
+    sqlx::query!(
+        r#"UPDATE users SET password = $1, updated_at = now() WHERE id = $2"#,
+        hash_pass,
+        auth_guard.user.id
+    )
+    .execute(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    log_out_everywhere(&data, &auth_guard.user).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "message": "Password changed, please login again"
+    })))
+}
+
+/// Schedules the account for deletion. Logging in again before the grace period
+/// is over cancels it.
+#[delete("/users/me")]
+pub async fn delete_me(
+    body: web::Json<DeleteAccountSchema>,
+    data: web::Data<AppState>,
+    auth_guard: JwtMiddleware,
+) -> Result<HttpResponse, CustomError> {
+    auth_guard.require(Scope::AccountAdmin)?;
+
+    ensure_password(&auth_guard.user, &body.password)?;
+
+    let deletion_requested_at = sqlx::query_scalar!(
+        r#"
+        UPDATE users
+        SET deletion_requested_at = COALESCE(deletion_requested_at, now()), updated_at = now()
+        WHERE id = $1
+        RETURNING deletion_requested_at AS "deletion_requested_at!"
+        "#,
+        auth_guard.user.id
+    )
+    .fetch_one(&data.db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    log_out_everywhere(&data, &auth_guard.user).await?;
+
+    let deleted_at = deletion_requested_at
+        + chrono::Duration::days(data.secrets.account_deletion_grace_days as i64);
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "message": "Account scheduled for deletion, login again before the deletion date to keep it",
+        "deletion_date": deleted_at
+    })))
+}
@@ -0,0 +1,109 @@
+use actix_web::error::{ErrorInternalServerError, ErrorUnauthorized};
+use actix_web::Error as ActixWebError;
+use actix_web::{http, HttpRequest};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+
+use crate::app_state::AppState;
+use crate::jwt_auth::ErrorResponse;
+use crate::models::api_key::ApiKey;
+use crate::models::user::User;
+
+/// Every key starts with this, which tells them apart from JWTs in `Authorization`.
+pub const API_KEY_PREFIX: &str = "sk_";
+pub const API_KEY_HEADER: &str = "X-API-Key";
+
+const API_KEY_LENGTH: usize = 40;
+/// Characters of the key kept in clear so users can tell their keys apart.
+const DISPLAY_PREFIX_LENGTH: usize = 11;
+
+pub fn generate_api_key() -> String {
+    let secret: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(API_KEY_LENGTH)
+        .map(char::from)
+        .collect();
+    format!("{}{}", API_KEY_PREFIX, secret)
+}
+
+/// Keys are long random strings, a plain SHA-256 is enough to store them safely.
+pub fn hash_api_key(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+pub fn display_prefix(key: &str) -> String {
+    key.chars().take(DISPLAY_PREFIX_LENGTH).collect()
+}
+
+/// The key sent with the request, either as `X-API-Key` or `Authorization: Bearer sk_...`.
+pub fn api_key_from_request(req: &HttpRequest) -> Option<String> {
+    let headers = req.headers();
+
+    if let Some(key) = headers
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+    {
+        return Some(key.trim().to_string());
+    }
+
+    headers
+        .get(http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| token.starts_with(API_KEY_PREFIX))
+        .map(str::to_string)
+}
+
+/// Looks the key up, records its use and loads its owner.
+pub async fn authenticate_api_key(
+    data: &AppState,
+    key: &str,
+) -> Result<(User, ApiKey), ActixWebError> {
+    let api_key = sqlx::query_as!(
+        ApiKey,
+        r#"
+        UPDATE api_keys SET last_used_at = now()
+        WHERE key_hash = $1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > now())
+        RETURNING *
+        "#,
+        hash_api_key(key)
+    )
+    .fetch_optional(&data.db)
+    .await
+    .map_err(|_| {
+        ErrorInternalServerError(ErrorResponse {
+            status: "error".to_string(),
+            message: "Failed to check the API key".to_string(),
+        })
+    })?
+    .ok_or_else(|| {
+        ErrorUnauthorized(ErrorResponse {
+            status: "fail".to_string(),
+            message: "The API key is invalid, expired or revoked".to_string(),
+        })
+    })?;
+
+    let user = sqlx::query_as!(
+        User,
+        r#"SELECT * FROM users WHERE id = $1 AND deletion_requested_at IS NULL"#,
+        api_key.user_id
+    )
+    .fetch_optional(&data.db)
+    .await
+    .map_err(|_| {
+        ErrorInternalServerError(ErrorResponse {
+            status: "error".to_string(),
+            message: "Failed to check user existence".to_string(),
+        })
+    })?
+    .ok_or_else(|| {
+        ErrorUnauthorized(ErrorResponse {
+            status: "fail".to_string(),
+            message: "the user belonging to this API key no longer exists or is being deleted"
+                .to_string(),
+        })
+    })?;
+
+    Ok((user, api_key))
+}
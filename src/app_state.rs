@@ -1,20 +1,48 @@
+use std::sync::Arc;
+
 use redis::Client;
 use sqlx::PgPool;
 
 use crate::config_env::Config;
+use crate::dns_resolver::TxtResolver;
+use crate::link_metadata::PageFetcher;
+use crate::mailer::Mailer;
+use crate::oidc::OidcClient;
+use crate::url_safety::UrlSafetyChecker;
 
 pub struct AppState {
     pub db: PgPool,
     pub secrets: Config,
     pub redis_client: Client,
+    pub dns_resolver: Arc<dyn TxtResolver>,
+    pub url_safety: Arc<dyn UrlSafetyChecker>,
+    pub page_fetcher: Arc<dyn PageFetcher>,
+    pub mailer: Arc<dyn Mailer>,
+    /// `None` when no OpenID Connect provider is configured.
+    pub oidc: Option<Arc<OidcClient>>,
 }
 
 impl AppState {
-    pub fn new(db: PgPool, secrets: Config, redis_client: Client) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        db: PgPool,
+        secrets: Config,
+        redis_client: Client,
+        dns_resolver: Arc<dyn TxtResolver>,
+        url_safety: Arc<dyn UrlSafetyChecker>,
+        page_fetcher: Arc<dyn PageFetcher>,
+        mailer: Arc<dyn Mailer>,
+        oidc: Option<Arc<OidcClient>>,
+    ) -> Self {
         Self {
             db,
             secrets,
             redis_client,
+            dns_resolver,
+            url_safety,
+            page_fetcher,
+            mailer,
+            oidc,
         }
     }
 }
@@ -1,8 +1,24 @@
 use std::env;
+use std::net::IpAddr;
 
 use dotenv::dotenv;
 use serde::Deserialize;
 
+use crate::utils::short_code::{
+    DEFAULT_BLOCKED_SHORT_CODE_WORDS, DEFAULT_RESERVED_SHORT_CODES, SHORT_CODE_MIN_LENGTH,
+};
+
+/// Built-in words plus the comma separated ones from `var`, lowercased.
+fn word_list(defaults: &[&str], var: &str) -> Vec<String> {
+    let extra = env::var(var).unwrap_or_default();
+    defaults
+        .iter()
+        .map(|word| word.to_string())
+        .chain(extra.split(',').map(|word| word.trim().to_lowercase()))
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
 fn parse_duration(duration_str: &str) -> Option<i64> {
     let mut numeric_part = String::new();
     for c in duration_str.chars() {
@@ -15,12 +31,26 @@ fn parse_duration(duration_str: &str) -> Option<i64> {
     numeric_part.parse::<i64>().ok()
 }
 
+/// The OpenID Connect provider used for single sign-on.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcConfig {
+    /// Its `/.well-known/openid-configuration` is read from here.
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    /// Where the provider sends the browser back with the code, either the callback
+    /// endpoint itself or a page of the client that forwards the query to it.
+    pub redirect_url: String,
+    pub scopes: String,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     pub database_url: String,
     pub redis_url: String,
     pub client_origin: String,
     pub domain: String,
+    pub public_url: String,
     pub access_token_private_key: String,
     pub access_token_public_key: String,
     pub access_token_expires_in: String,
@@ -29,6 +59,31 @@ pub struct Config {
     pub refresh_token_public_key: String,
     pub refresh_token_expires_in: String,
     pub refresh_token_max_age: i64,
+    pub url_blocklist_path: Option<String>,
+    pub check_url_safety_on_redirect: bool,
+    pub max_redirect_chain_depth: Option<usize>,
+    pub case_insensitive_short_urls: bool,
+    pub not_found_page: Option<String>,
+    pub not_found_redirect_url: Option<String>,
+    pub short_url_miss_retention_days: i32,
+    pub short_url_miss_max_rows: i64,
+    pub health_check_interval_secs: u64,
+    pub health_check_concurrency: usize,
+    pub health_check_failure_threshold: i32,
+    pub smtp_url: Option<String>,
+    pub mail_from: String,
+    pub mail_outbox_dir: Option<String>,
+    pub email_verification_token_max_age: i64,
+    pub password_reset_token_max_age: i64,
+    pub account_deletion_grace_days: i32,
+    pub login_max_attempts: u32,
+    pub login_ip_max_attempts: u32,
+    pub login_max_lockout_secs: u64,
+    /// Peers whose `Forwarded` / `X-Forwarded-For` headers are believed, nobody's by default.
+    pub trusted_proxies: Vec<IpAddr>,
+    pub oidc: Option<OidcConfig>,
+    pub reserved_short_codes: Vec<String>,
+    pub blocked_short_code_words: Vec<String>,
 }
 
 impl Config {
@@ -40,6 +95,10 @@ impl Config {
         let client_origin =
             env::var("CLIENT_ORIGIN").expect("CLIENT_ORIGIN must be set in .env file");
         let domain = env::var("DOMAIN").expect("DOMAIN must be set in .env file");
+        let public_url = env::var("PUBLIC_URL")
+            .unwrap_or_else(|_| format!("https://{}", domain))
+            .trim_end_matches('/')
+            .to_string();
         let access_token_private_key = env::var("ACCESS_TOKEN_PRIVATE_KEY")
             .expect("ACCESS_TOKEN_PRIVATE_KEY must be set in .env file");
         let access_token_public_key = env::var("ACCESS_TOKEN_PUBLIC_KEY")
@@ -56,12 +115,137 @@ impl Config {
             .expect("REFRESH_TOKEN_EXPIRES_IN must be set in .env file");
         let refresh_token_max_age = env::var("REFRESH_TOKEN_MAXAGE")
             .expect("REFRESH_TOKEN_MAXAGE must be set in .env file");
+        let url_blocklist_path = env::var("URL_BLOCKLIST_PATH").ok();
+        let check_url_safety_on_redirect = env::var("URL_SAFETY_CHECK_ON_REDIRECT")
+            .map(|value| value == "true" || value == "1")
+            .unwrap_or(false);
+        let max_redirect_chain_depth = env::var("MAX_REDIRECT_CHAIN_DEPTH").ok().map(|depth| {
+            depth
+                .parse::<usize>()
+                .unwrap_or_else(|_| panic!("Invalid MAX_REDIRECT_CHAIN_DEPTH: {}", depth))
+        });
+        let case_insensitive_short_urls = env::var("CASE_INSENSITIVE_SHORT_URLS")
+            .map(|value| value == "true" || value == "1")
+            .unwrap_or(false);
+        let not_found_page = env::var("NOT_FOUND_PAGE_PATH").ok().map(|path| {
+            std::fs::read_to_string(&path)
+                .unwrap_or_else(|err| panic!("Cannot read NOT_FOUND_PAGE_PATH {}: {}", path, err))
+        });
+        let not_found_redirect_url = env::var("NOT_FOUND_REDIRECT_URL").ok();
+        let short_url_miss_retention_days = env::var("SHORT_URL_MISS_RETENTION_DAYS")
+            .map(|days| {
+                days.parse::<i32>()
+                    .unwrap_or_else(|_| panic!("Invalid SHORT_URL_MISS_RETENTION_DAYS: {}", days))
+            })
+            .unwrap_or(30);
+        let short_url_miss_max_rows = env::var("SHORT_URL_MISS_MAX_ROWS")
+            .map(|count| {
+                count
+                    .parse::<i64>()
+                    .unwrap_or_else(|_| panic!("Invalid SHORT_URL_MISS_MAX_ROWS: {}", count))
+            })
+            .unwrap_or(10_000);
+        let health_check_interval_secs = env::var("HEALTH_CHECK_INTERVAL_SECS")
+            .map(|secs| {
+                secs.parse::<u64>()
+                    .unwrap_or_else(|_| panic!("Invalid HEALTH_CHECK_INTERVAL_SECS: {}", secs))
+            })
+            .unwrap_or(6 * 60 * 60);
+        let health_check_concurrency = env::var("HEALTH_CHECK_CONCURRENCY")
+            .map(|count| {
+                count
+                    .parse::<usize>()
+                    .unwrap_or_else(|_| panic!("Invalid HEALTH_CHECK_CONCURRENCY: {}", count))
+            })
+            .unwrap_or(8);
+        let health_check_failure_threshold = env::var("HEALTH_CHECK_FAILURE_THRESHOLD")
+            .map(|count| {
+                count
+                    .parse::<i32>()
+                    .unwrap_or_else(|_| panic!("Invalid HEALTH_CHECK_FAILURE_THRESHOLD: {}", count))
+            })
+            .unwrap_or(3);
+        let smtp_url = env::var("SMTP_URL").ok();
+        let mail_from = env::var("MAIL_FROM").unwrap_or_else(|_| format!("no-reply@{}", domain));
+        let mail_outbox_dir = env::var("MAIL_OUTBOX_DIR").ok();
+        let email_verification_token_max_age = env::var("EMAIL_VERIFICATION_TOKEN_MAXAGE")
+            .map(|age| {
+                parse_duration(&age)
+                    .unwrap_or_else(|| panic!("Invalid EMAIL_VERIFICATION_TOKEN_MAXAGE: {}", age))
+            })
+            .unwrap_or(24 * 60);
+        let password_reset_token_max_age = env::var("PASSWORD_RESET_TOKEN_MAXAGE")
+            .map(|age| {
+                parse_duration(&age)
+                    .unwrap_or_else(|| panic!("Invalid PASSWORD_RESET_TOKEN_MAXAGE: {}", age))
+            })
+            .unwrap_or(60);
+        let account_deletion_grace_days = env::var("ACCOUNT_DELETION_GRACE_DAYS")
+            .map(|days| {
+                days.parse::<i32>()
+                    .unwrap_or_else(|_| panic!("Invalid ACCOUNT_DELETION_GRACE_DAYS: {}", days))
+            })
+            .unwrap_or(14);
+        let login_max_attempts = env::var("LOGIN_MAX_ATTEMPTS")
+            .map(|count| {
+                count
+                    .parse::<u32>()
+                    .unwrap_or_else(|_| panic!("Invalid LOGIN_MAX_ATTEMPTS: {}", count))
+            })
+            .unwrap_or(5);
+        let login_ip_max_attempts = env::var("LOGIN_IP_MAX_ATTEMPTS")
+            .map(|count| {
+                count
+                    .parse::<u32>()
+                    .unwrap_or_else(|_| panic!("Invalid LOGIN_IP_MAX_ATTEMPTS: {}", count))
+            })
+            .unwrap_or(20);
+        let login_max_lockout_secs = env::var("LOGIN_MAX_LOCKOUT_SECS")
+            .map(|secs| {
+                secs.parse::<u64>()
+                    .unwrap_or_else(|_| panic!("Invalid LOGIN_MAX_LOCKOUT_SECS: {}", secs))
+            })
+            .unwrap_or(15 * 60);
+        let trusted_proxies = env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|ip| !ip.is_empty())
+            .map(|ip| {
+                ip.parse::<IpAddr>()
+                    .unwrap_or_else(|_| panic!("Invalid TRUSTED_PROXIES entry: {}", ip))
+            })
+            .collect();
+        let oidc = env::var("OIDC_ISSUER_URL")
+            .ok()
+            .map(|issuer_url| OidcConfig {
+                issuer_url: issuer_url.trim_end_matches('/').to_string(),
+                client_id: env::var("OIDC_CLIENT_ID")
+                    .expect("OIDC_CLIENT_ID must be set when OIDC_ISSUER_URL is"),
+                client_secret: env::var("OIDC_CLIENT_SECRET").ok(),
+                redirect_url: env::var("OIDC_REDIRECT_URL")
+                    .unwrap_or_else(|_| format!("{}/api/auth/oidc/callback", public_url)),
+                scopes: env::var("OIDC_SCOPES")
+                    .unwrap_or_else(|_| "openid email profile".to_string()),
+            });
+
+        let reserved_short_codes = word_list(&DEFAULT_RESERVED_SHORT_CODES, "RESERVED_SHORT_CODES");
+        if let Some(code) = reserved_short_codes
+            .iter()
+            .find(|code| code.len() < SHORT_CODE_MIN_LENGTH)
+        {
+            panic!(
+                "Invalid RESERVED_SHORT_CODES: {} is shorter than {} characters, no short URL can use it",
+                code, SHORT_CODE_MIN_LENGTH
+            );
+        }
 
         Config {
             database_url,
             redis_url,
             client_origin,
             domain,
+            public_url,
             access_token_private_key,
             access_token_public_key,
             access_token_expires_in,
@@ -72,6 +256,38 @@ impl Config {
             refresh_token_expires_in,
             refresh_token_max_age: parse_duration(&refresh_token_max_age)
                 .unwrap_or_else(|| panic!("Invalid duration: {}", refresh_token_max_age)),
+            url_blocklist_path,
+            check_url_safety_on_redirect,
+            max_redirect_chain_depth,
+            case_insensitive_short_urls,
+            not_found_page,
+            not_found_redirect_url,
+            short_url_miss_retention_days,
+            short_url_miss_max_rows,
+            health_check_interval_secs,
+            health_check_concurrency,
+            health_check_failure_threshold,
+            smtp_url,
+            mail_from,
+            mail_outbox_dir,
+            email_verification_token_max_age,
+            password_reset_token_max_age,
+            account_deletion_grace_days,
+            login_max_attempts,
+            login_ip_max_attempts,
+            login_max_lockout_secs,
+            trusted_proxies,
+            oidc,
+            reserved_short_codes,
+            blocked_short_code_words: word_list(
+                &DEFAULT_BLOCKED_SHORT_CODE_WORDS,
+                "BLOCKED_SHORT_CODE_WORDS",
+            ),
         }
     }
+
+    /// Public address of a short link, as it is handed out to visitors.
+    pub fn short_link(&self, short_url: &str) -> String {
+        format!("{}/api/url/redirect/{}", self.public_url, short_url)
+    }
 }
@@ -1,4 +1,4 @@
-use actix_web::http::header::ContentType;
+use actix_web::http::header::{self, ContentType};
 use actix_web::{error::ResponseError, http::StatusCode, HttpResponse};
 
 use serde_json::json;
@@ -9,6 +9,8 @@ use redis::RedisError as redisError;
 
 use sqlx::Error as DbError;
 
+use crate::scope::Scope;
+
 #[derive(Debug, Error)]
 pub enum CustomError {
     #[error("Other error: {0}")]
@@ -119,11 +121,88 @@ pub enum CustomHttpError {
     RecordNotFound,
     #[error("Url not found with the given ID")]
     UrlNotFound,
+    #[error("Domain not found with the given ID")]
+    DomainNotFound,
+    #[error("The domain provided was already added, please use another one.")]
+    DomainAlreadyExists,
+    #[error("The domain has not been verified yet, please verify it before using it.")]
+    DomainNotVerified,
+    #[error("The verification TXT record was not found on the domain, please try again later.")]
+    DomainVerificationFailed,
+    #[error("The short URL is already taken on this domain, please choose another one.")]
+    ShortUrlAlreadyExists,
+    #[error("Some short URLs of this domain only differ by case, please rename them before making it case-insensitive.")]
+    ShortUrlCaseCollision,
+    #[error("The destination URL was blocked because it looks unsafe: {0}")]
+    UnsafeUrl(String),
+    #[error("The destination points back to this short URL and would create a redirect loop.")]
+    RedirectLoop,
+    #[error(
+        "The destination is a chain of more than {0} short URLs, please link to the final URL."
+    )]
+    RedirectChainTooDeep(usize),
+    #[error("This action requires an administrator account.")]
+    AdminOnly,
+    #[error("The short URL is reserved, please choose another one.")]
+    ShortUrlReserved,
+    #[error("The short URL contains words that are not allowed, please choose another one.")]
+    ShortUrlNotAllowed,
+    #[error("Collection not found with the given ID")]
+    CollectionNotFound,
+    #[error("A collection cannot be moved inside itself or one of its sub collections.")]
+    CollectionCycle,
+    #[error("Revision not found for the given URL")]
+    RevisionNotFound,
+    #[error("API key not found with the given ID")]
+    ApiKeyNotFound,
+    #[error("The expiry date of an API key must be in the future.")]
+    ApiKeyExpiryInPast,
+    #[error("Please verify your email address first, check your inbox for the verification link.")]
+    EmailNotVerified,
+    #[error("The email address is already verified.")]
+    EmailAlreadyVerified,
+    #[error("The verification link is invalid or has expired, please request a new one.")]
+    VerificationTokenInvalid,
+    #[error("The password reset link is invalid, expired or was already used, please request a new one.")]
+    ResetTokenInvalid,
+    #[error("Two-factor authentication is already enabled.")]
+    TwoFactorAlreadyEnabled,
+    #[error("Two-factor authentication is not enabled.")]
+    TwoFactorNotEnabled,
+    #[error("Start the two-factor enrollment before confirming it.")]
+    TwoFactorNotEnrolled,
+    #[error("The two-factor code is not valid, please try again.")]
+    TwoFactorCodeInvalid,
+    #[error("The login attempt expired or is not valid, please login again.")]
+    MfaTokenInvalid,
+    #[error("Too many failed login attempts, please try again in {0} seconds.")]
+    TooManyLoginAttempts(u64),
+    #[error("Session not found with the given ID")]
+    SessionNotFound,
+    #[error("This refresh token was already used, every session started from it has been revoked. Please login again.")]
+    RefreshTokenReused,
+    #[error("This credential is missing the '{0}' scope required for this action.")]
+    MissingScope(Scope),
+    #[error("Could not fetch the destination page: {0}")]
+    MetadataFetchFailed(String),
+    #[error("Single sign-on is not configured on this server.")]
+    OidcNotConfigured,
+    #[error("The single sign-on provider could not be reached or sent an invalid answer: {0}")]
+    OidcProviderError(String),
+    #[error("The single sign-on attempt expired or is not valid, please try again.")]
+    OidcStateInvalid,
+    #[error("The single sign-on provider did not confirm your email address.")]
+    OidcEmailNotVerified,
 }
 
 impl ResponseError for CustomHttpError {
     fn error_response(&self) -> HttpResponse {
-        HttpResponse::build(self.status_code())
+        let mut response = HttpResponse::build(self.status_code());
+        if let CustomHttpError::TooManyLoginAttempts(retry_after) = self {
+            response.insert_header((header::RETRY_AFTER, retry_after.to_string()));
+        }
+
+        response
             .insert_header(ContentType::json())
             .json(serde_json::json!({
                 "status": "error",
@@ -147,6 +226,41 @@ impl ResponseError for CustomHttpError {
             CustomHttpError::RecordNotFound => StatusCode::NOT_FOUND,
             CustomHttpError::UrlNotFound => StatusCode::NOT_FOUND,
             CustomHttpError::UserNotFound => StatusCode::NOT_FOUND,
+            CustomHttpError::DomainNotFound => StatusCode::NOT_FOUND,
+            CustomHttpError::DomainAlreadyExists => StatusCode::CONFLICT,
+            CustomHttpError::DomainNotVerified => StatusCode::FORBIDDEN,
+            CustomHttpError::DomainVerificationFailed => StatusCode::BAD_REQUEST,
+            CustomHttpError::ShortUrlAlreadyExists => StatusCode::CONFLICT,
+            CustomHttpError::ShortUrlCaseCollision => StatusCode::CONFLICT,
+            CustomHttpError::UnsafeUrl(_) => StatusCode::FORBIDDEN,
+            CustomHttpError::RedirectLoop => StatusCode::BAD_REQUEST,
+            CustomHttpError::RedirectChainTooDeep(_) => StatusCode::BAD_REQUEST,
+            CustomHttpError::AdminOnly => StatusCode::FORBIDDEN,
+            CustomHttpError::ShortUrlReserved => StatusCode::FORBIDDEN,
+            CustomHttpError::ShortUrlNotAllowed => StatusCode::BAD_REQUEST,
+            CustomHttpError::MetadataFetchFailed(_) => StatusCode::BAD_GATEWAY,
+            CustomHttpError::CollectionNotFound => StatusCode::NOT_FOUND,
+            CustomHttpError::CollectionCycle => StatusCode::BAD_REQUEST,
+            CustomHttpError::RevisionNotFound => StatusCode::NOT_FOUND,
+            CustomHttpError::ApiKeyNotFound => StatusCode::NOT_FOUND,
+            CustomHttpError::ApiKeyExpiryInPast => StatusCode::BAD_REQUEST,
+            CustomHttpError::MissingScope(_) => StatusCode::FORBIDDEN,
+            CustomHttpError::RefreshTokenReused => StatusCode::UNAUTHORIZED,
+            CustomHttpError::SessionNotFound => StatusCode::NOT_FOUND,
+            CustomHttpError::TooManyLoginAttempts(_) => StatusCode::TOO_MANY_REQUESTS,
+            CustomHttpError::TwoFactorAlreadyEnabled => StatusCode::CONFLICT,
+            CustomHttpError::TwoFactorNotEnabled => StatusCode::BAD_REQUEST,
+            CustomHttpError::TwoFactorNotEnrolled => StatusCode::BAD_REQUEST,
+            CustomHttpError::TwoFactorCodeInvalid => StatusCode::UNAUTHORIZED,
+            CustomHttpError::MfaTokenInvalid => StatusCode::UNAUTHORIZED,
+            CustomHttpError::ResetTokenInvalid => StatusCode::BAD_REQUEST,
+            CustomHttpError::EmailNotVerified => StatusCode::FORBIDDEN,
+            CustomHttpError::EmailAlreadyVerified => StatusCode::CONFLICT,
+            CustomHttpError::VerificationTokenInvalid => StatusCode::BAD_REQUEST,
+            CustomHttpError::OidcNotConfigured => StatusCode::NOT_FOUND,
+            CustomHttpError::OidcProviderError(_) => StatusCode::BAD_GATEWAY,
+            CustomHttpError::OidcStateInvalid => StatusCode::BAD_REQUEST,
+            CustomHttpError::OidcEmailNotVerified => StatusCode::FORBIDDEN,
         }
     }
 }
@@ -157,13 +271,12 @@ pub fn handle_validation_error(
     let error_message = validation_error
         .field_errors()
         .values()
-        .map(|errors| {
+        .flat_map(|errors| {
             errors
                 .iter()
                 .map(|err| err.to_string())
                 .collect::<Vec<String>>()
         })
-        .flatten()
         .collect::<Vec<String>>()
         .join(", ");
 
@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use futures::future::BoxFuture;
+
+use hickory_resolver::TokioAsyncResolver;
+
+use crate::custom_error::CustomError;
+
+/// Looks up the TXT records of a name. Domain verification goes through this
+/// trait so it can be swapped for a fake resolver outside of production.
+pub trait TxtResolver: Send + Sync {
+    fn txt_records<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<String>, CustomError>>;
+}
+
+pub struct SystemTxtResolver {
+    resolver: TokioAsyncResolver,
+}
+
+impl SystemTxtResolver {
+    pub fn from_system_conf() -> Result<Self, CustomError> {
+        let resolver = TokioAsyncResolver::tokio_from_system_conf()
+            .map_err(|err| CustomError::OtherError(format!("DNS resolver error: {}", err)))?;
+        Ok(Self { resolver })
+    }
+}
+
+impl TxtResolver for SystemTxtResolver {
+    fn txt_records<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<String>, CustomError>> {
+        Box::pin(async move {
+            let lookup = match self.resolver.txt_lookup(name).await {
+                Ok(lookup) => lookup,
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        hickory_resolver::error::ResolveErrorKind::NoRecordsFound { .. }
+                    ) =>
+                {
+                    return Ok(Vec::new())
+                }
+                Err(err) => {
+                    return Err(CustomError::OtherError(format!(
+                        "Could not resolve TXT records for {}: {}",
+                        name, err
+                    )))
+                }
+            };
+
+            Ok(lookup
+                .iter()
+                .map(|txt| {
+                    txt.txt_data()
+                        .iter()
+                        .map(|chunk| String::from_utf8_lossy(chunk))
+                        .collect::<String>()
+                })
+                .collect())
+        })
+    }
+}
+
+/// Answers from a fixed table instead of DNS, for tests and local setups without
+/// control over a real zone. Names missing from the table have no records.
+#[derive(Default)]
+pub struct StaticTxtResolver {
+    records: HashMap<String, Vec<String>>,
+}
+
+impl StaticTxtResolver {
+    pub fn with_record(mut self, name: &str, value: &str) -> Self {
+        self.records
+            .entry(name.to_lowercase())
+            .or_default()
+            .push(value.to_string());
+        self
+    }
+}
+
+impl TxtResolver for StaticTxtResolver {
+    fn txt_records<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<String>, CustomError>> {
+        Box::pin(async move {
+            Ok(self
+                .records
+                .get(&name.to_lowercase())
+                .cloned()
+                .unwrap_or_default())
+        })
+    }
+}
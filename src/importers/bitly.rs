@@ -0,0 +1,63 @@
+use crate::custom_error::CustomError;
+
+use super::{import_error, parse_clicks, parse_timestamp, short_code_from_link, ImportedLink};
+
+const LONG_URL_HEADERS: [&str; 4] = ["long_url", "long url", "destination", "original_url"];
+const LINK_HEADERS: [&str; 5] = ["link", "bitlink", "short_url", "short link", "id"];
+const CUSTOM_LINK_HEADERS: [&str; 2] = ["custom_bitlinks", "custom bitlinks"];
+const CLICKS_HEADERS: [&str; 4] = ["clicks", "total_clicks", "total clicks", "engagements"];
+const CREATED_AT_HEADERS: [&str; 3] = ["created_at", "date created", "created"];
+
+fn find_column(headers: &csv::StringRecord, names: &[&str]) -> Option<usize> {
+    headers
+        .iter()
+        .position(|header| names.contains(&header.trim().to_lowercase().as_str()))
+}
+
+/// Parses the CSV export Bitly offers under "Links > Export".
+pub fn parse_csv(content: &str) -> Result<Vec<ImportedLink>, CustomError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+
+    let headers = reader
+        .headers()
+        .map_err(|err| import_error(format!("Invalid Bitly CSV header: {}", err)))?
+        .clone();
+
+    let long_url_column = find_column(&headers, &LONG_URL_HEADERS)
+        .ok_or_else(|| import_error("The Bitly CSV must contain a long_url column".to_string()))?;
+    let link_column = find_column(&headers, &LINK_HEADERS);
+    let custom_link_column = find_column(&headers, &CUSTOM_LINK_HEADERS);
+    let clicks_column = find_column(&headers, &CLICKS_HEADERS);
+    let created_at_column = find_column(&headers, &CREATED_AT_HEADERS);
+
+    let mut links = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let row = index + 1;
+        let record = record
+            .map_err(|err| import_error(format!("Invalid Bitly CSV row {}: {}", row, err)))?;
+        let field = |column: Option<usize>| {
+            column
+                .and_then(|column| record.get(column))
+                .filter(|value| !value.is_empty())
+        };
+
+        // Custom back-halves are what people actually typed, so prefer them.
+        let short_url = field(custom_link_column)
+            .and_then(|links| links.split(',').next())
+            .or_else(|| field(link_column))
+            .and_then(short_code_from_link);
+
+        links.push(ImportedLink {
+            row,
+            original_url: field(Some(long_url_column)).unwrap_or_default().to_string(),
+            short_url,
+            views: field(clicks_column).map(parse_clicks).unwrap_or(0),
+            created_at: field(created_at_column).and_then(parse_timestamp),
+        });
+    }
+
+    Ok(links)
+}
@@ -0,0 +1,71 @@
+pub mod bitly;
+pub mod yourls;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::custom_error::{CustomError, ValidationModelsErrors};
+use crate::models::url::ImportSource;
+
+/// A link read from a third party export, before it is mapped onto a `Url` row.
+#[derive(Debug)]
+pub struct ImportedLink {
+    pub row: usize,
+    pub original_url: String,
+    pub short_url: Option<String>,
+    pub views: i32,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+pub fn parse_import(source: ImportSource, body: &[u8]) -> Result<Vec<ImportedLink>, CustomError> {
+    let content = std::str::from_utf8(body)
+        .map_err(|_| import_error("The import file must be UTF-8 encoded".to_string()))?;
+
+    match source {
+        ImportSource::Bitly => bitly::parse_csv(content),
+        ImportSource::Yourls => match content.trim_start().chars().next() {
+            Some('[') | Some('{') => yourls::parse_json(content),
+            _ => yourls::parse_sql(content),
+        },
+    }
+}
+
+pub(crate) fn import_error(message: String) -> CustomError {
+    CustomError::ValidationError(ValidationModelsErrors::Error(message))
+}
+
+pub(crate) fn parse_clicks(value: &str) -> i32 {
+    value
+        .trim()
+        .parse::<i64>()
+        .map(|clicks| clicks.clamp(0, i32::MAX as i64) as i32)
+        .unwrap_or(0)
+}
+
+pub(crate) fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    if let Ok(date) = DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%z") {
+        return Some(date.with_timezone(&Utc));
+    }
+
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .map(|date| date.and_utc())
+}
+
+/// Takes the code out of a short link such as `https://bit.ly/3xYzAbC`.
+pub(crate) fn short_code_from_link(link: &str) -> Option<String> {
+    let link = link.trim();
+    let without_query = link.split(['?', '#']).next().unwrap_or(link);
+    let code = without_query.trim_end_matches('/').rsplit('/').next()?;
+
+    if code.is_empty() || code.contains('.') {
+        None
+    } else {
+        Some(code.to_string())
+    }
+}
@@ -0,0 +1,320 @@
+use serde_json::Value;
+
+use crate::custom_error::CustomError;
+
+use super::{import_error, parse_clicks, parse_timestamp, ImportedLink};
+
+/// Column order of the links table, used when an INSERT has no column list.
+const DEFAULT_COLUMNS: [&str; 6] = ["keyword", "url", "title", "timestamp", "ip", "clicks"];
+
+fn link_from_fields(row: usize, field: impl Fn(&str) -> Option<String>) -> ImportedLink {
+    ImportedLink {
+        row,
+        original_url: field("url").unwrap_or_default(),
+        short_url: field("keyword").filter(|keyword| !keyword.is_empty()),
+        views: field("clicks")
+            .map(|clicks| parse_clicks(&clicks))
+            .unwrap_or(0),
+        created_at: field("timestamp").and_then(|timestamp| parse_timestamp(&timestamp)),
+    }
+}
+
+/// Parses a JSON dump of the `yourls_url` table, either a plain array of rows or
+/// the phpMyAdmin export format which nests the rows under a `data` key.
+pub fn parse_json(content: &str) -> Result<Vec<ImportedLink>, CustomError> {
+    let value: Value = serde_json::from_str(content)
+        .map_err(|err| import_error(format!("Invalid YOURLS JSON dump: {}", err)))?;
+
+    let mut rows = Vec::new();
+    collect_rows(&value, &mut rows);
+
+    Ok(rows
+        .into_iter()
+        .enumerate()
+        .map(|(index, row)| {
+            link_from_fields(index + 1, |name| match row.get(name) {
+                Some(Value::String(value)) => Some(value.clone()),
+                Some(Value::Number(value)) => Some(value.to_string()),
+                _ => None,
+            })
+        })
+        .collect())
+}
+
+fn collect_rows<'a>(value: &'a Value, rows: &mut Vec<&'a serde_json::Map<String, Value>>) {
+    match value {
+        Value::Object(object) if object.contains_key("keyword") && object.contains_key("url") => {
+            rows.push(object)
+        }
+        Value::Object(object) => object.values().for_each(|value| collect_rows(value, rows)),
+        Value::Array(values) => values.iter().for_each(|value| collect_rows(value, rows)),
+        _ => {}
+    }
+}
+
+/// The links table is `<prefix>url`, `yourls_url` with the default prefix. The other
+/// tables of an install (`yourls_log`, `yourls_options`) never end in `_url`.
+fn is_links_table(table: &str) -> bool {
+    let table = table.to_ascii_lowercase();
+    table == "url" || table.ends_with("_url")
+}
+
+/// Parses the `INSERT INTO yourls_url ...` statements of a mysqldump or phpMyAdmin SQL
+/// export, whatever the table prefix. Statements for any other table are ignored.
+pub fn parse_sql(content: &str) -> Result<Vec<ImportedLink>, CustomError> {
+    let mut parser = SqlParser::new(content);
+    let mut links = Vec::new();
+    let mut found_table = false;
+
+    while parser.seek_keyword("INSERT") {
+        if !parser.keyword("INTO") {
+            continue;
+        }
+        let table = parser.identifier().unwrap_or_default();
+        if !is_links_table(&table) {
+            continue;
+        }
+        found_table = true;
+
+        let columns = if parser.peek() == Some('(') {
+            parser.identifier_list()?
+        } else {
+            DEFAULT_COLUMNS.iter().map(|c| c.to_string()).collect()
+        };
+
+        if !parser.keyword("VALUES") {
+            return Err(import_error(format!(
+                "Expected VALUES after INSERT INTO {}",
+                table
+            )));
+        }
+
+        loop {
+            let values = parser.value_tuple()?;
+            let row = links.len() + 1;
+            links.push(link_from_fields(row, |name| {
+                columns
+                    .iter()
+                    .position(|column| column == name)
+                    .and_then(|index| values.get(index).cloned().flatten())
+            }));
+
+            if parser.peek() == Some(',') {
+                parser.advance();
+            } else {
+                break;
+            }
+        }
+    }
+
+    if !found_table {
+        return Err(import_error(
+            "No rows of a YOURLS links table (such as yourls_url) found in the SQL dump"
+                .to_string(),
+        ));
+    }
+
+    Ok(links)
+}
+
+struct SqlParser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> SqlParser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, position: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.rest().chars().next()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.rest().chars().next()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    /// Moves past the next `keyword` that starts a word, skipping string literals,
+    /// quoted identifiers and comments, where the word means nothing.
+    fn seek_keyword(&mut self, keyword: &str) -> bool {
+        while let Some(c) = self.rest().chars().next() {
+            let rest = self.rest();
+            let skipped = match c {
+                '\'' => self.quoted_string().is_ok(),
+                '"' | '`' => self.skip_past(1, &c.to_string()),
+                '#' => self.skip_past(1, "\n"),
+                '-' if rest.starts_with("--") => self.skip_past(2, "\n"),
+                '/' if rest.starts_with("/*") => self.skip_past(2, "*/"),
+                c if c.is_alphanumeric() || c == '_' => {
+                    let end = rest
+                        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                        .unwrap_or(rest.len());
+                    self.position += end;
+                    if rest[..end].eq_ignore_ascii_case(keyword) {
+                        return true;
+                    }
+                    true
+                }
+                _ => self.advance().is_some(),
+            };
+            if !skipped {
+                break;
+            }
+        }
+        self.position = self.input.len();
+        false
+    }
+
+    /// Skips `opening` bytes, then everything up to and including `closing`.
+    fn skip_past(&mut self, opening: usize, closing: &str) -> bool {
+        self.position += opening;
+        match self.rest().find(closing) {
+            Some(end) => {
+                self.position += end + closing.len();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        self.skip_whitespace();
+        // `get` rather than indexing, the keyword may end inside a multibyte character.
+        let matches = self
+            .rest()
+            .get(..keyword.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(keyword));
+        if matches {
+            self.position += keyword.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn identifier(&mut self) -> Option<String> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let end = rest
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '`' | '"' | '.')))
+            .unwrap_or(rest.len());
+        if end == 0 {
+            return None;
+        }
+        self.position += end;
+        let identifier = rest[..end].replace(['`', '"'], "");
+        Some(
+            identifier
+                .rsplit('.')
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+        )
+    }
+
+    fn identifier_list(&mut self) -> Result<Vec<String>, CustomError> {
+        self.expect('(')?;
+        let mut identifiers = Vec::new();
+        loop {
+            identifiers.push(
+                self.identifier()
+                    .ok_or_else(|| import_error("Invalid column list in INSERT".to_string()))?,
+            );
+            match self.peek() {
+                Some(',') => {
+                    self.advance();
+                }
+                _ => break,
+            }
+        }
+        self.expect(')')?;
+        Ok(identifiers)
+    }
+
+    fn value_tuple(&mut self) -> Result<Vec<Option<String>>, CustomError> {
+        self.expect('(')?;
+        let mut values = Vec::new();
+        loop {
+            values.push(self.value()?);
+            match self.peek() {
+                Some(',') => {
+                    self.advance();
+                }
+                _ => break,
+            }
+        }
+        self.expect(')')?;
+        Ok(values)
+    }
+
+    fn value(&mut self) -> Result<Option<String>, CustomError> {
+        match self.peek() {
+            Some('\'') => self.quoted_string().map(Some),
+            Some(_) => {
+                let rest = self.rest();
+                let end = rest
+                    .find(|c: char| c == ',' || c == ')' || c.is_whitespace())
+                    .unwrap_or(rest.len());
+                self.position += end;
+                let token = &rest[..end];
+                if token.eq_ignore_ascii_case("NULL") {
+                    Ok(None)
+                } else {
+                    Ok(Some(token.to_string()))
+                }
+            }
+            None => Err(import_error("Unexpected end of SQL dump".to_string())),
+        }
+    }
+
+    fn quoted_string(&mut self) -> Result<String, CustomError> {
+        self.expect('\'')?;
+        let mut value = String::new();
+        loop {
+            match self.advance() {
+                Some('\\') => match self.advance() {
+                    Some('n') => value.push('\n'),
+                    Some('r') => value.push('\r'),
+                    Some('t') => value.push('\t'),
+                    Some('0') => value.push('\0'),
+                    Some(c) => value.push(c),
+                    None => break,
+                },
+                Some('\'') if self.rest().starts_with('\'') => {
+                    self.advance();
+                    value.push('\'');
+                }
+                Some('\'') => return Ok(value),
+                Some(c) => value.push(c),
+                None => break,
+            }
+        }
+        Err(import_error("Unterminated string in SQL dump".to_string()))
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), CustomError> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.advance();
+                Ok(())
+            }
+            found => Err(import_error(format!(
+                "Invalid SQL dump: expected '{}' but found {:?}",
+                expected, found
+            ))),
+        }
+    }
+}
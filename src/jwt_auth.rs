@@ -8,14 +8,17 @@ use futures::executor::block_on;
 use redis::Commands;
 use serde::{Deserialize, Serialize};
 
+use crate::api_key_auth::{api_key_from_request, authenticate_api_key};
 use crate::app_state::AppState;
+use crate::custom_error::{CustomError, CustomHttpError};
 use crate::models::user::User;
+use crate::scope::{parse_scopes, Scope};
 use crate::token::token::verify_jwt_token;
 
 #[derive(Debug, Serialize)]
-struct ErrorResponse {
-    status: String,
-    message: String,
+pub(crate) struct ErrorResponse {
+    pub(crate) status: String,
+    pub(crate) message: String,
 }
 
 impl fmt::Display for ErrorResponse {
@@ -27,7 +30,37 @@ impl fmt::Display for ErrorResponse {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JwtMiddleware {
     pub user: User,
-    pub access_token_uuid: uuid::Uuid,
+    /// Set when the request carries an access token.
+    pub access_token_uuid: Option<uuid::Uuid>,
+    /// Set when the request carries an API key instead.
+    pub api_key_id: Option<uuid::Uuid>,
+    pub scopes: Vec<Scope>,
+}
+
+impl JwtMiddleware {
+    /// Fails with a 403 naming the scope when the credential was not granted it.
+    pub fn require(&self, scope: Scope) -> Result<(), CustomError> {
+        if self.scopes.contains(&scope) {
+            Ok(())
+        } else {
+            Err(CustomError::HttpError(CustomHttpError::MissingScope(scope)))
+        }
+    }
+
+    /// Fails with a 403 until the user has confirmed their email address.
+    pub fn require_verified_email(&self) -> Result<(), CustomError> {
+        if self.user.email_verified_at.is_some() {
+            Ok(())
+        } else {
+            Err(CustomError::HttpError(CustomHttpError::EmailNotVerified))
+        }
+    }
+
+    /// Whether admin privileges apply: an admin's key without `account:admin` stays a
+    /// regular user's key.
+    pub fn acts_as_admin(&self) -> bool {
+        self.user.is_admin && self.scopes.contains(&Scope::AccountAdmin)
+    }
 }
 
 impl FromRequest for JwtMiddleware {
@@ -36,6 +69,17 @@ impl FromRequest for JwtMiddleware {
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
         let data = req.app_data::<web::Data<AppState>>().unwrap();
 
+        if let Some(key) = api_key_from_request(req) {
+            return ready(
+                block_on(authenticate_api_key(data, &key)).map(|(user, api_key)| JwtMiddleware {
+                    user,
+                    access_token_uuid: None,
+                    api_key_id: Some(api_key.id),
+                    scopes: parse_scopes(&api_key.scopes),
+                }),
+            );
+        }
+
         let bearer_token = req
             .headers()
             .get(http::header::AUTHORIZATION)
@@ -72,6 +116,7 @@ impl FromRequest for JwtMiddleware {
 
         let access_token_uuid =
             uuid::Uuid::parse_str(&access_token_details.token_uuid.to_string()).unwrap();
+        let scopes = access_token_details.scopes;
 
         let user_id_redis_result = async move {
             let mut redis_client = match data.redis_client.get_connection() {
@@ -99,17 +144,23 @@ impl FromRequest for JwtMiddleware {
             let user_id = user_id_redis_result.await?;
             let user_id_uuid = uuid::Uuid::parse_str(user_id.as_str()).unwrap();
 
-            let query_result =
-                sqlx::query_as!(User, r#"SELECT * FROM users WHERE id = $1"#, user_id_uuid)
-                    .fetch_optional(&data.db)
-                    .await;
+            // Same as for API keys, logging in again is what cancels a pending deletion.
+            let query_result = sqlx::query_as!(
+                User,
+                r#"SELECT * FROM users WHERE id = $1 AND deletion_requested_at IS NULL"#,
+                user_id_uuid
+            )
+            .fetch_optional(&data.db)
+            .await;
 
             match query_result {
                 Ok(Some(user)) => Ok(user),
                 Ok(None) => {
                     let json_error = ErrorResponse {
                         status: "fail".to_string(),
-                        message: "the user belonging to this token no logger exists".to_string(),
+                        message:
+                            "the user belonging to this token no longer exists or is being deleted"
+                                .to_string(),
                     };
                     Err(ErrorUnauthorized(json_error))
                 }
@@ -125,7 +176,9 @@ impl FromRequest for JwtMiddleware {
 
         match block_on(user_exists_result) {
             Ok(user) => ready(Ok(JwtMiddleware {
-                access_token_uuid,
+                access_token_uuid: Some(access_token_uuid),
+                api_key_id: None,
+                scopes,
                 user,
             })),
             Err(error) => ready(Err(error)),
@@ -1,9 +1,24 @@
+pub mod account_deletion;
 pub mod api;
+pub mod api_key_auth;
 pub mod config_env;
 pub mod custom_error;
+pub mod dns_resolver;
+pub mod importers;
 pub mod jwt_auth;
+pub mod link_health;
+pub mod link_metadata;
+pub mod login_throttle;
+pub mod mailer;
 pub mod models;
+pub mod oidc;
+pub mod redirect_chain;
+pub mod scope;
+pub mod short_url_case;
+pub mod short_url_misses;
 pub mod token;
+pub mod totp;
+pub mod url_safety;
 
 pub mod app_state;
 pub mod tests;
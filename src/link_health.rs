@@ -0,0 +1,130 @@
+//! Scheduled checks that the destinations of stored links still answer.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::StreamExt;
+use sqlx::PgPool;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::custom_error::{CustomError, CustomHttpError};
+use crate::link_metadata::PageFetcher;
+
+#[derive(Debug)]
+pub struct HealthCheck {
+    pub url_id: Uuid,
+    pub status_code: Option<u16>,
+    pub latency_ms: i32,
+    pub error: Option<String>,
+}
+
+impl HealthCheck {
+    /// Anything below 400 once redirects are followed counts as healthy.
+    pub fn is_healthy(&self) -> bool {
+        self.error.is_none() && self.status_code.is_some_and(|status| status < 400)
+    }
+}
+
+async fn probe(fetcher: &dyn PageFetcher, url_id: Uuid, original_url: String) -> HealthCheck {
+    let started = Instant::now();
+    let result = fetcher.probe(&original_url).await;
+    let latency_ms = started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+
+    match result {
+        Ok(status) => HealthCheck {
+            url_id,
+            status_code: Some(status),
+            latency_ms,
+            error: None,
+        },
+        Err(CustomError::HttpError(CustomHttpError::MetadataFetchFailed(reason))) => HealthCheck {
+            url_id,
+            status_code: None,
+            latency_ms,
+            error: Some(reason),
+        },
+        Err(err) => HealthCheck {
+            url_id,
+            status_code: None,
+            latency_ms,
+            error: Some(err.to_string()),
+        },
+    }
+}
+
+async fn record(db: &PgPool, check: &HealthCheck) -> Result<(), CustomError> {
+    sqlx::query!(
+        r#"
+        UPDATE urls
+        SET health_status_code = $1, health_latency_ms = $2, health_error = $3,
+            health_checked_at = now(),
+            health_failures = CASE WHEN $4 THEN 0 ELSE health_failures + 1 END
+        WHERE id = $5
+        "#,
+        check.status_code.map(i32::from),
+        check.latency_ms,
+        check.error,
+        check.is_healthy(),
+        check.url_id
+    )
+    .execute(db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    Ok(())
+}
+
+/// Probes every stored destination with at most `concurrency` requests in flight,
+/// returns how many links were checked and recorded.
+pub async fn check_link_health(
+    db: &PgPool,
+    fetcher: &dyn PageFetcher,
+    concurrency: usize,
+) -> Result<usize, CustomError> {
+    let links =
+        sqlx::query!(r#"SELECT id, original_url FROM urls ORDER BY health_checked_at NULLS FIRST"#)
+            .fetch_all(db)
+            .await
+            .map_err(CustomError::DataBaseError)?;
+
+    let mut checks = futures::stream::iter(links)
+        .map(|link| probe(fetcher, link.id, link.original_url))
+        .buffer_unordered(concurrency.max(1));
+
+    // One link failing to save must not cost the rest of the run its results.
+    let mut checked = 0;
+    while let Some(check) = checks.next().await {
+        match record(db, &check).await {
+            Ok(()) => checked += 1,
+            Err(err) => warn!(
+                "Failed to record the health of link {}: {}",
+                check.url_id, err
+            ),
+        }
+    }
+
+    Ok(checked)
+}
+
+/// Runs `check_link_health` every `every`, a zero interval disables the checks.
+pub async fn watch_link_health(
+    db: PgPool,
+    fetcher: Arc<dyn PageFetcher>,
+    every: Duration,
+    concurrency: usize,
+) {
+    if every.is_zero() {
+        return;
+    }
+
+    let mut interval = actix_web::rt::time::interval(every);
+    loop {
+        interval.tick().await;
+
+        match check_link_health(&db, fetcher.as_ref(), concurrency).await {
+            Ok(count) => info!("Checked the destination of {} links", count),
+            Err(err) => warn!("Failed to check link destinations: {}", err),
+        }
+    }
+}
@@ -0,0 +1,430 @@
+//! Title, description, preview image and favicon of link destinations.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use hickory_resolver::TokioAsyncResolver;
+use regex::Regex;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use sqlx::PgPool;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::custom_error::{CustomError, CustomHttpError};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_BODY_BYTES: usize = 512 * 1024;
+const MAX_REDIRECTS: usize = 5;
+const MAX_FIELD_CHARS: usize = 1024;
+
+lazy_static::lazy_static! {
+    static ref TITLE_REGEX: Regex = Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap();
+    static ref TAG_REGEX: Regex = Regex::new(r"(?is)<(meta|link)\s[^>]*>").unwrap();
+    static ref ATTRIBUTE_REGEX: Regex =
+        Regex::new(r#"(?s)([a-zA-Z_:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap();
+}
+
+/// A page downloaded for metadata extraction, `url` is the address after redirects.
+#[derive(Debug)]
+pub struct FetchedPage {
+    pub url: url::Url,
+    pub html: Option<String>,
+}
+
+/// Downloads destination pages. Metadata extraction and health checks go through
+/// this trait so they can point at a stub server instead of the internet.
+pub trait PageFetcher: Send + Sync {
+    fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<FetchedPage, CustomError>>;
+
+    /// Status code the destination finally answers with, after redirects.
+    fn probe<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<u16, CustomError>>;
+}
+
+fn fetch_error(message: impl Into<String>) -> CustomError {
+    CustomError::HttpError(CustomHttpError::MetadataFetchFailed(message.into()))
+}
+
+/// reqwest keeps the interesting part, such as a refused address, in the source chain.
+fn request_error(err: reqwest::Error) -> CustomError {
+    let mut message = err.to_string();
+    let mut source = std::error::Error::source(&err);
+    while let Some(cause) = source {
+        message = format!("{}: {}", message, cause);
+        source = cause.source();
+    }
+    fetch_error(message)
+}
+
+/// Addresses that must never be reached on behalf of a user: loopback, private,
+/// link-local, shared, multicast and reserved ranges.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_public_ipv4(mapped),
+            None => is_public_ipv6(ip),
+        },
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        || a >= 240
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 192 && b == 0 && c == 0)
+        || (a == 198 && (18..20).contains(&b)))
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80
+        || first == 0x2001 && ip.segments()[1] == 0x0db8
+        || first == 0x0064 && ip.segments()[1] == 0xff9b)
+}
+
+/// Resolver handed to the HTTP client so every connection, redirects included,
+/// is refused when the name points at a non public address.
+struct PublicOnlyResolver {
+    resolver: Arc<TokioAsyncResolver>,
+}
+
+impl Resolve for PublicOnlyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.resolver.clone();
+        Box::pin(async move {
+            let lookup = resolver.lookup_ip(name.as_str()).await?;
+            let addrs: Vec<SocketAddr> = lookup.iter().map(|ip| SocketAddr::new(ip, 0)).collect();
+
+            if addrs.is_empty() || addrs.iter().any(|addr| !is_public_ip(addr.ip())) {
+                return Err(format!("{} resolves to a non public address", name.as_str()).into());
+            }
+
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+pub struct HttpPageFetcher {
+    client: reqwest::Client,
+    allow_private_networks: bool,
+}
+
+impl HttpPageFetcher {
+    /// `allow_private_networks` turns the SSRF protection off, for local stub servers only.
+    pub fn new(allow_private_networks: bool) -> Result<Self, CustomError> {
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .user_agent(concat!("url-shortener-preview/", env!("CARGO_PKG_VERSION")));
+
+        if !allow_private_networks {
+            let resolver = TokioAsyncResolver::tokio_from_system_conf()
+                .map_err(|err| CustomError::OtherError(format!("DNS resolver error: {}", err)))?;
+            builder = builder.dns_resolver(Arc::new(PublicOnlyResolver {
+                resolver: Arc::new(resolver),
+            }));
+        }
+
+        let client = builder
+            .build()
+            .map_err(|err| CustomError::OtherError(format!("HTTP client error: {}", err)))?;
+
+        Ok(Self {
+            client,
+            allow_private_networks,
+        })
+    }
+
+    fn check_target(&self, url: &url::Url) -> Result<(), CustomError> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(fetch_error(format!("unsupported scheme {}", url.scheme())));
+        }
+
+        // Literal addresses never go through the resolver, check them here.
+        let literal = match url.host() {
+            Some(url::Host::Ipv4(ip)) => Some(IpAddr::V4(ip)),
+            Some(url::Host::Ipv6(ip)) => Some(IpAddr::V6(ip)),
+            Some(url::Host::Domain(_)) => None,
+            None => return Err(fetch_error("the URL has no host")),
+        };
+
+        match literal {
+            Some(ip) if !self.allow_private_networks && !is_public_ip(ip) => {
+                Err(fetch_error(format!("{} is not a public address", ip)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn read_body(mut response: reqwest::Response) -> Result<String, CustomError> {
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(request_error)? {
+            let room = MAX_BODY_BYTES - body.len();
+            body.extend_from_slice(&chunk[..chunk.len().min(room)]);
+            // The head is all we need, a truncated page still has it.
+            if body.len() >= MAX_BODY_BYTES {
+                break;
+            }
+        }
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+}
+
+impl HttpPageFetcher {
+    /// Sends the request and follows redirects, checking every hop, until a final answer.
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+    ) -> Result<(url::Url, reqwest::Response), CustomError> {
+        let mut current = url::Url::parse(url).map_err(|err| fetch_error(err.to_string()))?;
+
+        for _ in 0..=MAX_REDIRECTS {
+            self.check_target(&current)?;
+
+            let response = self
+                .client
+                .request(method.clone(), current.clone())
+                .header(reqwest::header::ACCEPT, "text/html,application/xhtml+xml")
+                .send()
+                .await
+                .map_err(request_error)?;
+
+            if !response.status().is_redirection() {
+                return Ok((current, response));
+            }
+
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|value| value.to_str().ok())
+                .ok_or_else(|| fetch_error("redirect without a location"))?;
+            current = current
+                .join(location)
+                .map_err(|err| fetch_error(err.to_string()))?;
+        }
+
+        Err(fetch_error(format!(
+            "more than {} redirects",
+            MAX_REDIRECTS
+        )))
+    }
+}
+
+impl PageFetcher for HttpPageFetcher {
+    fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<FetchedPage, CustomError>> {
+        Box::pin(async move {
+            let (url, response) = self.send(reqwest::Method::GET, url).await?;
+
+            if !response.status().is_success() {
+                return Err(fetch_error(format!(
+                    "the destination answered {}",
+                    response.status()
+                )));
+            }
+
+            let is_html = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.contains("html"));
+
+            let html = if is_html {
+                Some(Self::read_body(response).await?)
+            } else {
+                None
+            };
+
+            Ok(FetchedPage { url, html })
+        })
+    }
+
+    fn probe<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<u16, CustomError>> {
+        Box::pin(async move {
+            let (_, response) = self.send(reqwest::Method::HEAD, url).await?;
+
+            // Plenty of servers do not implement HEAD, ask again with a GET.
+            let status = match response.status() {
+                reqwest::StatusCode::METHOD_NOT_ALLOWED | reqwest::StatusCode::NOT_IMPLEMENTED => {
+                    self.send(reqwest::Method::GET, url).await?.1.status()
+                }
+                status => status,
+            };
+
+            Ok(status.as_u16())
+        })
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct LinkMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+    pub favicon_url: Option<String>,
+}
+
+fn decode_entities(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+fn clean_text(value: &str) -> Option<String> {
+    let text = decode_entities(value)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    match text.is_empty() {
+        true => None,
+        false => Some(text.chars().take(MAX_FIELD_CHARS).collect()),
+    }
+}
+
+fn absolute_url(base: &url::Url, value: &str) -> Option<String> {
+    base.join(decode_entities(value.trim()).as_str())
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .map(String::from)
+}
+
+impl LinkMetadata {
+    /// Reads the `<title>`, the description and Open Graph `<meta>` tags and the icon
+    /// `<link>` tags. Open Graph values win over the plain ones.
+    pub fn from_page(page: &FetchedPage) -> Self {
+        let mut metadata = LinkMetadata::default();
+        let html = page.html.as_deref().unwrap_or_default();
+        let mut og_title = None;
+        let mut og_description = None;
+
+        for tag in TAG_REGEX.captures_iter(html) {
+            let attributes: Vec<(String, String)> = ATTRIBUTE_REGEX
+                .captures_iter(&tag[0])
+                .map(|attribute| {
+                    let value = attribute
+                        .get(2)
+                        .or(attribute.get(3))
+                        .or(attribute.get(4))
+                        .map_or("", |value| value.as_str());
+                    (attribute[1].to_lowercase(), value.to_string())
+                })
+                .collect();
+            let attribute = |name: &str| {
+                attributes
+                    .iter()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.as_str())
+            };
+
+            if tag[1].eq_ignore_ascii_case("link") {
+                let is_icon = attribute("rel").is_some_and(|rel| {
+                    rel.split_whitespace()
+                        .any(|rel| rel.eq_ignore_ascii_case("icon"))
+                });
+                if is_icon && metadata.favicon_url.is_none() {
+                    metadata.favicon_url =
+                        attribute("href").and_then(|href| absolute_url(&page.url, href));
+                }
+                continue;
+            }
+
+            let key = attribute("property")
+                .or(attribute("name"))
+                .unwrap_or_default()
+                .to_lowercase();
+            let content = attribute("content").unwrap_or_default();
+            match key.as_str() {
+                "og:title" => og_title = og_title.or(clean_text(content)),
+                "og:description" => og_description = og_description.or(clean_text(content)),
+                "description" if metadata.description.is_none() => {
+                    metadata.description = clean_text(content)
+                }
+                "og:image" | "og:image:url" if metadata.image_url.is_none() => {
+                    metadata.image_url = absolute_url(&page.url, content)
+                }
+                _ => {}
+            }
+        }
+
+        metadata.title = og_title.or_else(|| {
+            TITLE_REGEX
+                .captures(html)
+                .and_then(|title| clean_text(&title[1]))
+        });
+        metadata.description = og_description.or(metadata.description);
+
+        if metadata.favicon_url.is_none() {
+            metadata.favicon_url = absolute_url(&page.url, "/favicon.ico");
+        }
+
+        metadata
+    }
+}
+
+/// Fetches the destination of a link and stores what was found on it.
+pub async fn refresh_link_metadata(
+    db: &PgPool,
+    fetcher: &dyn PageFetcher,
+    url_id: Uuid,
+    original_url: &str,
+) -> Result<LinkMetadata, CustomError> {
+    let page = fetcher.fetch(original_url).await?;
+    let metadata = LinkMetadata::from_page(&page);
+
+    // The destination may have been edited while the page was downloading.
+    sqlx::query!(
+        r#"
+        UPDATE urls
+        SET meta_title = $1, meta_description = $2, meta_image_url = $3,
+            meta_favicon_url = $4, metadata_fetched_at = now()
+        WHERE id = $5 AND original_url = $6
+        "#,
+        metadata.title,
+        metadata.description,
+        metadata.image_url,
+        metadata.favicon_url,
+        url_id,
+        original_url
+    )
+    .execute(db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    Ok(metadata)
+}
+
+/// Runs `refresh_link_metadata` in the background, failures are only logged.
+pub fn spawn_metadata_refresh(
+    db: PgPool,
+    fetcher: Arc<dyn PageFetcher>,
+    url_id: Uuid,
+    original_url: String,
+) {
+    actix_web::rt::spawn(async move {
+        if let Err(err) = refresh_link_metadata(&db, fetcher.as_ref(), url_id, &original_url).await
+        {
+            warn!("Could not fetch metadata of {}: {}", original_url, err);
+        }
+    });
+}
@@ -0,0 +1,97 @@
+//! Brute-force protection for logins. Failed attempts are counted in Redis per IP and per
+//! account, past a few free attempts every failure locks the key out twice as long as the
+//! previous one, up to `login_max_lockout_secs`.
+
+use redis::aio::Connection;
+use redis::{AsyncCommands, RedisResult};
+use tracing::warn;
+
+use crate::config_env::Config;
+
+/// Failures are forgotten after an hour without any.
+const FAILURE_WINDOW_SECS: usize = 60 * 60;
+
+#[derive(Debug, Clone, Copy)]
+pub enum ThrottleKey<'a> {
+    Ip(&'a str),
+    /// The lowercased email, whether an account exists for it or not.
+    Account(&'a str),
+}
+
+impl ThrottleKey<'_> {
+    fn name(&self) -> String {
+        match self {
+            ThrottleKey::Ip(ip) => format!("ip:{}", ip),
+            ThrottleKey::Account(email) => format!("account:{}", email),
+        }
+    }
+
+    fn failures_key(&self) -> String {
+        format!("login_failures:{}", self.name())
+    }
+
+    fn lockout_key(&self) -> String {
+        format!("login_lockout:{}", self.name())
+    }
+
+    /// Many users can share an IP behind a NAT, so it gets more room than an account.
+    fn free_attempts(&self, config: &Config) -> u32 {
+        match self {
+            ThrottleKey::Ip(_) => config.login_ip_max_attempts,
+            ThrottleKey::Account(_) => config.login_max_attempts,
+        }
+    }
+}
+
+/// Seconds until every lockout among `keys` is over, `None` when none is locked out.
+pub async fn locked_out_for(
+    redis: &mut Connection,
+    keys: &[ThrottleKey<'_>],
+) -> RedisResult<Option<u64>> {
+    let mut longest = None;
+    for key in keys {
+        let ttl: i64 = redis.ttl(key.lockout_key()).await?;
+        if ttl > 0 {
+            longest = longest.max(Some(ttl as u64));
+        }
+    }
+    Ok(longest)
+}
+
+pub async fn record_failure(
+    redis: &mut Connection,
+    config: &Config,
+    keys: &[ThrottleKey<'_>],
+) -> RedisResult<()> {
+    for key in keys {
+        let failures: u32 = redis.incr(key.failures_key(), 1).await?;
+        redis
+            .expire::<_, ()>(key.failures_key(), FAILURE_WINDOW_SECS)
+            .await?;
+
+        let free_attempts = key.free_attempts(config);
+        if failures >= free_attempts {
+            let doublings = (failures - free_attempts).min(32);
+            let lockout_secs = (1u64 << doublings).min(config.login_max_lockout_secs);
+
+            redis
+                .set_ex::<_, _, ()>(key.lockout_key(), failures, lockout_secs as usize)
+                .await?;
+            warn!(
+                target: "security",
+                key = %key.name(),
+                failures,
+                lockout_secs,
+                "Too many failed logins, locking out"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Clears the account after a successful login. The IP keeps its count, logging into
+/// one account must not reset the guessing on others.
+pub async fn record_success(redis: &mut Connection, email: &str) -> RedisResult<()> {
+    let key = ThrottleKey::Account(email);
+    redis.del(&[key.failures_key(), key.lockout_key()]).await
+}
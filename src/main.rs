@@ -1,3 +1,5 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
 use actix_web::{web::Data, App, HttpServer};
 
 use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
@@ -5,13 +7,23 @@ use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
 use redis::Client;
 
 use url_shortener_api::{
-    api::handler::config_handler, app_state::AppState, config_env, custom_error::CustomError,
+    account_deletion::watch_account_deletions,
+    api::handler::config_handler,
+    app_state::AppState,
+    config_env,
+    custom_error::CustomError,
+    dns_resolver::{SystemTxtResolver, TxtResolver},
+    link_health::watch_link_health,
+    link_metadata::{HttpPageFetcher, PageFetcher},
+    mailer::{FileMailer, Mailer, SmtpMailer},
+    oidc::OidcClient,
+    short_url_case::sync_shared_case_index,
+    short_url_misses::watch_short_url_misses,
+    url_safety::{watch_blocklist, BlocklistChecker, UrlSafetyChecker},
 };
 
 use tracing::{info, warn};
 
-use tracing_subscriber;
-
 #[actix_web::main]
 async fn main() -> Result<(), CustomError> {
     let config_data = config_env::Config::init();
@@ -39,6 +51,11 @@ async fn main() -> Result<(), CustomError> {
         })?;
 
     info!("Migrations ran successfully");
+
+    // Lookups keep working without the index, only the uniqueness is missing.
+    if let Err(err) = sync_shared_case_index(&pool, config_data.case_insensitive_short_urls).await {
+        warn!("Case-insensitive short URLs are not unique: {}", err);
+    }
     info!("Server started successfully 🚀!");
 
     let redis_client = Client::open(config_data.redis_url.to_owned()).map_err(|err| {
@@ -46,12 +63,65 @@ async fn main() -> Result<(), CustomError> {
         CustomError::RedisError(err)
     })?;
 
+    let dns_resolver: Arc<dyn TxtResolver> = Arc::new(SystemTxtResolver::from_system_conf()?);
+
+    let url_safety: Arc<dyn UrlSafetyChecker> = Arc::new(BlocklistChecker::new(
+        config_data.url_blocklist_path.as_ref().map(PathBuf::from),
+    )?);
+
+    let page_fetcher: Arc<dyn PageFetcher> = Arc::new(HttpPageFetcher::new(false)?);
+
+    let mailer: Arc<dyn Mailer> = match &config_data.smtp_url {
+        Some(smtp_url) => Arc::new(SmtpMailer::new(smtp_url, &config_data.mail_from)?),
+        None => Arc::new(FileMailer::new(
+            config_data.mail_outbox_dir.as_ref().map(PathBuf::from),
+        )?),
+    };
+
+    let oidc = config_data
+        .oidc
+        .clone()
+        .map(OidcClient::new)
+        .transpose()?
+        .map(Arc::new);
+
+    actix_web::rt::spawn(watch_blocklist(
+        pool.clone(),
+        url_safety.clone(),
+        Duration::from_secs(5),
+    ));
+
+    actix_web::rt::spawn(watch_link_health(
+        pool.clone(),
+        page_fetcher.clone(),
+        Duration::from_secs(config_data.health_check_interval_secs),
+        config_data.health_check_concurrency,
+    ));
+
+    actix_web::rt::spawn(watch_account_deletions(
+        pool.clone(),
+        config_data.account_deletion_grace_days,
+        Duration::from_secs(60 * 60),
+    ));
+
+    actix_web::rt::spawn(watch_short_url_misses(
+        pool.clone(),
+        config_data.short_url_miss_retention_days,
+        config_data.short_url_miss_max_rows,
+        Duration::from_secs(60 * 60),
+    ));
+
     HttpServer::new(move || {
         App::new()
             .app_data(Data::new(AppState {
                 db: pool.clone(),
                 secrets: config_data.clone(),
                 redis_client: redis_client.clone(),
+                dns_resolver: dns_resolver.clone(),
+                url_safety: url_safety.clone(),
+                page_fetcher: page_fetcher.clone(),
+                mailer: mailer.clone(),
+                oidc: oidc.clone(),
             }))
             .configure(|ctx| config_handler(ctx, &config_data))
     })
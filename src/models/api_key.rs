@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use validator::Validate;
+
+use crate::scope::Scope;
+
+#[derive(Debug, FromRow, Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    pub scopes: Vec<String>,
+    #[serde(rename = "lastUsedAt")]
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "revokedAt")]
+    pub revoked_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateApiKey {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        code = "code_str",
+        message = "API key name must be between 1 and 100 characters"
+    ))]
+    pub name: String,
+    /// Defaults to reading and writing links.
+    pub scopes: Option<Vec<Scope>>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiKeyPath {
+    pub api_key_id: Uuid,
+}
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use validator::Validate;
+
+#[derive(Debug, FromRow, Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct Collection {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub name: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A collection with the links filed directly in it and in all of its descendants.
+#[derive(Debug, Serialize)]
+pub struct CollectionSummary {
+    pub id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub name: String,
+    pub link_count: i64,
+    pub views: i64,
+    pub total_link_count: i64,
+    pub total_views: i64,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Tells a missing field apart from an explicit `null`, which moves to the root.
+pub fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateCollection {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        code = "code_str",
+        message = "Collection name must be between 1 and 100 characters"
+    ))]
+    pub name: String,
+    pub parent_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateCollection {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        code = "code_str",
+        message = "Collection name must be between 1 and 100 characters"
+    ))]
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub parent_id: Option<Option<Uuid>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CollectionPath {
+    pub collection_id: Uuid,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DeleteMode {
+    /// Deletes the sub collections and every link filed in them.
+    Cascade,
+    /// Moves the sub collections and links up to the parent of the deleted collection.
+    #[default]
+    Reparent,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteCollectionQuery {
+    pub mode: Option<DeleteMode>,
+}
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use validator::Validate;
+
+lazy_static::lazy_static! {
+    static ref HOSTNAME_REGEX: regex::Regex = regex::Regex::new(
+        r"^([a-z0-9]([a-z0-9-]{0,61}[a-z0-9])?\.)+[a-z]{2,63}$"
+    )
+    .unwrap();
+}
+
+/// Name of the TXT record holding the verification token, relative to the hostname.
+pub const VERIFICATION_RECORD_PREFIX: &str = "_url-shortener";
+
+#[derive(Debug, FromRow, Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct Domain {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub hostname: String,
+    pub verification_token: String,
+    #[serde(rename = "caseInsensitive")]
+    pub case_insensitive: bool,
+    #[serde(rename = "verifiedAt")]
+    pub verified_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl Domain {
+    pub fn verification_record_name(&self) -> String {
+        format!("{}.{}", VERIFICATION_RECORD_PREFIX, self.hostname)
+    }
+
+    pub fn short_link(&self, short_url: &str) -> String {
+        format!("https://{}/{}", self.hostname, short_url)
+    }
+
+    pub fn verification_record_value(&self) -> String {
+        format!("url-shortener-verification={}", self.verification_token)
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateDomain {
+    #[validate(regex(
+        path = "HOSTNAME_REGEX",
+        code = "code_str",
+        message = "Invalid hostname, please provide a domain such as go.example.com"
+    ))]
+    pub hostname: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateDomain {
+    pub case_insensitive: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DomainPath {
+    pub domain_id: Uuid,
+}
+
+/// Strips the port and normalises the case of a `Host` header value.
+pub fn normalize_host(host: &str) -> String {
+    host.rsplit_once(':')
+        .filter(|(_, port)| port.chars().all(|c| c.is_ascii_digit()))
+        .map_or(host, |(hostname, _)| hostname)
+        .trim_end_matches('.')
+        .to_lowercase()
+}
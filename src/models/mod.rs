@@ -1,2 +1,8 @@
+pub mod api_key;
+pub mod collection;
+pub mod domain;
+pub mod oidc;
+pub mod session;
+pub mod two_factor;
 pub mod url;
 pub mod user;
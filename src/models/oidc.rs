@@ -0,0 +1,9 @@
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: String,
+    /// Set by the provider instead of `code` when the user refused or the login failed.
+    pub error: Option<String>,
+}
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A login, from the password check to the expiry of the last refresh token rotated from it.
+#[derive(Debug, Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct Session {
+    pub id: Uuid,
+    pub device: String,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "lastSeenAt")]
+    pub last_seen_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SessionPath {
+    pub session_id: Uuid,
+}
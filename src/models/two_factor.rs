@@ -0,0 +1,20 @@
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmTwoFactorSchema {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MfaLoginSchema {
+    pub mfa_token: String,
+    /// A TOTP code or a recovery code.
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DisableTwoFactorSchema {
+    pub password: String,
+    /// A TOTP code or a recovery code.
+    pub code: String,
+}
@@ -8,8 +8,14 @@ use core::fmt;
 
 use validator::Validate;
 
+use crate::models::collection::deserialize_some;
+
+use crate::utils::short_code::SHORT_CODE_MIN_LENGTH;
+
 lazy_static::lazy_static! {
-    static ref SHORT_URL_REGEX: regex::Regex = regex::Regex::new(r"^[a-zA-Z0-9_ ]{5,30}$").unwrap();
+    pub static ref SHORT_URL_REGEX: regex::Regex =
+        regex::Regex::new(&format!(r"^[a-zA-Z0-9_ ]{{{},30}}$", SHORT_CODE_MIN_LENGTH)).unwrap();
+    static ref HEX_COLOR_REGEX: regex::Regex = regex::Regex::new(r"^#?[0-9a-fA-F]{6}$").unwrap();
 }
 
 #[derive(Debug, FromRow, Deserialize, Serialize)]
@@ -19,9 +25,42 @@ pub struct Url {
     pub original_url: String,
     pub short_url: String,
     pub user_id: Option<Uuid>,
+    pub domain_id: Option<Uuid>,
+    pub collection_id: Option<Uuid>,
     pub views: Option<i32>,
     pub category: UrlCategory,
     pub slug: String,
+    pub title: Option<String>,
+    pub notes: Option<String>,
+    #[serde(rename = "isFavorite")]
+    pub is_favorite: bool,
+    #[serde(rename = "flaggedAt")]
+    pub flagged_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "flagReason")]
+    pub flag_reason: Option<String>,
+    #[serde(rename = "metaTitle")]
+    pub meta_title: Option<String>,
+    #[serde(rename = "metaDescription")]
+    pub meta_description: Option<String>,
+    #[serde(rename = "metaImageUrl")]
+    pub meta_image_url: Option<String>,
+    #[serde(rename = "metaFaviconUrl")]
+    pub meta_favicon_url: Option<String>,
+    #[serde(rename = "metadataFetchedAt")]
+    pub metadata_fetched_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "healthStatusCode")]
+    pub health_status_code: Option<i32>,
+    #[serde(rename = "healthLatencyMs")]
+    pub health_latency_ms: Option<i32>,
+    #[serde(rename = "healthError")]
+    pub health_error: Option<String>,
+    #[serde(rename = "healthCheckedAt")]
+    pub health_checked_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "healthFailures")]
+    pub health_failures: i32,
+    /// Copy of the domain's setting, kept up to date by the database.
+    #[serde(skip)]
+    pub case_insensitive: bool,
     #[serde(default)]
     #[serde(rename = "createdAt")]
     pub created_at: Option<chrono::DateTime<chrono::Utc>>,
@@ -45,6 +84,22 @@ pub struct CreateUrl {
     ))]
     pub short_url: String,
     pub category: UrlCategory,
+    pub domain_id: Option<Uuid>,
+    pub collection_id: Option<Uuid>,
+    #[validate(length(
+        max = 255,
+        code = "code_str",
+        message = "Title must be at most 255 characters"
+    ))]
+    pub title: Option<String>,
+    /// Markdown, stored as written.
+    #[validate(length(
+        max = 10000,
+        code = "code_str",
+        message = "Notes must be at most 10000 characters"
+    ))]
+    pub notes: Option<String>,
+    pub is_favorite: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -63,26 +118,200 @@ pub struct UpdateUrl {
     ))]
     pub short_url: Option<String>,
     pub category: Option<UrlCategory>,
+    /// `null` takes the link out of its collection.
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub collection_id: Option<Option<Uuid>>,
+    #[validate(length(
+        max = 255,
+        code = "code_str",
+        message = "Title must be at most 255 characters"
+    ))]
+    pub title: Option<String>,
+    /// Markdown, stored as written.
+    #[validate(length(
+        max = 10000,
+        code = "code_str",
+        message = "Notes must be at most 10000 characters"
+    ))]
+    pub notes: Option<String>,
+    pub is_favorite: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UrlRecord {
     pub user_id: Uuid,
     pub id: Uuid,
+    pub domain_id: Option<Uuid>,
+    pub collection_id: Option<Uuid>,
     pub views: Option<i32>,
     pub original_url: String,
     pub short_url: String,
     pub category: UrlCategory,
     pub slug: String,
+    pub title: Option<String>,
+    pub notes: Option<String>,
+    pub is_favorite: bool,
+    pub flagged_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub flag_reason: Option<String>,
+    pub meta_title: Option<String>,
+    pub meta_description: Option<String>,
+    pub meta_image_url: Option<String>,
+    pub meta_favicon_url: Option<String>,
+    pub metadata_fetched_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub health_status_code: Option<i32>,
+    pub health_latency_ms: Option<i32>,
+    pub health_error: Option<String>,
+    pub health_checked_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub health_failures: i32,
     pub created_at: Option<chrono::DateTime<chrono::Utc>>,
     pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
+impl UrlRecord {
+    pub fn new(user_id: Uuid, url: Url) -> Self {
+        Self {
+            user_id,
+            id: url.id,
+            domain_id: url.domain_id,
+            collection_id: url.collection_id,
+            views: url.views,
+            original_url: url.original_url,
+            short_url: url.short_url,
+            category: url.category,
+            slug: url.slug,
+            title: url.title,
+            notes: url.notes,
+            is_favorite: url.is_favorite,
+            flagged_at: url.flagged_at,
+            flag_reason: url.flag_reason,
+            meta_title: url.meta_title,
+            meta_description: url.meta_description,
+            meta_image_url: url.meta_image_url,
+            meta_favicon_url: url.meta_favicon_url,
+            metadata_fetched_at: url.metadata_fetched_at,
+            health_status_code: url.health_status_code,
+            health_latency_ms: url.health_latency_ms,
+            health_error: url.health_error,
+            health_checked_at: url.health_checked_at,
+            health_failures: url.health_failures,
+            created_at: url.created_at,
+            updated_at: url.updated_at,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct UrlQuery {
     pub limit: Option<i64>,
     pub offset: Option<i64>,
     pub category: Option<UrlCategory>,
+    pub favorite: Option<bool>,
+    pub collection_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    pub format: Option<ExportFormat>,
+    /// Comma separated list of `UrlRecord` fields, all of them when missing.
+    pub columns: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportSource {
+    Bitly,
+    Yourls,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportQuery {
+    pub source: ImportSource,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RenamedLink {
+    pub original_code: String,
+    pub short_url: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkippedLink {
+    pub row: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub total: usize,
+    pub imported: usize,
+    pub preserved: usize,
+    pub renamed: Vec<RenamedLink>,
+    pub skipped: Vec<SkippedLink>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum QrFormat {
+    Png,
+    Svg,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub enum QrErrorCorrection {
+    L,
+    M,
+    Q,
+    H,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct QrQuery {
+    pub format: Option<QrFormat>,
+    #[validate(range(
+        min = 64,
+        max = 2048,
+        message = "QR size must be between 64 and 2048 pixels"
+    ))]
+    pub size: Option<u32>,
+    #[validate(range(max = 16, message = "QR margin must be at most 16 modules"))]
+    pub margin: Option<u32>,
+    pub ecc: Option<QrErrorCorrection>,
+    #[validate(regex(
+        path = "HEX_COLOR_REGEX",
+        message = "Foreground colour must be a hex colour such as #000000"
+    ))]
+    pub fg: Option<String>,
+    #[validate(regex(
+        path = "HEX_COLOR_REGEX",
+        message = "Background colour must be a hex colour such as #ffffff"
+    ))]
+    pub bg: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
@@ -137,6 +366,31 @@ pub struct UrlPath {
     pub url_id: Uuid,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RevisionPath {
+    pub url_id: Uuid,
+    pub revision_id: Uuid,
+}
+
+/// State of a link right after one of its changes.
+#[derive(Debug, FromRow, Deserialize, Serialize)]
+pub struct UrlRevision {
+    pub id: Uuid,
+    pub url_id: Uuid,
+    #[serde(rename = "changedBy")]
+    pub changed_by: Option<Uuid>,
+    pub original_url: String,
+    pub short_url: String,
+    pub category: UrlCategory,
+    pub title: Option<String>,
+    pub notes: Option<String>,
+    #[serde(rename = "isFavorite")]
+    pub is_favorite: bool,
+    pub collection_id: Option<Uuid>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct UrlPathRedirect {
     pub short_url: String,
@@ -144,5 +398,7 @@ pub struct UrlPathRedirect {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OriginalUrl {
+    pub id: Uuid,
     pub original_url: String,
+    pub flag_reason: Option<String>,
 }
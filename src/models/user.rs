@@ -18,6 +18,18 @@ pub struct User {
     pub name: String,
     pub email: String,
     pub password: String,
+    #[serde(rename = "isAdmin")]
+    pub is_admin: bool,
+    #[serde(rename = "emailVerifiedAt")]
+    pub email_verified_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "deletionRequestedAt")]
+    pub deletion_requested_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(skip_serializing)]
+    pub totp_secret: Option<String>,
+    #[serde(rename = "totpEnabledAt")]
+    pub totp_enabled_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(skip_serializing)]
+    pub totp_last_used_step: Option<i64>,
     #[serde(rename = "createdAt")]
     pub created_at: Option<chrono::DateTime<chrono::Utc>>,
     #[serde(rename = "updatedAt")]
@@ -58,6 +70,70 @@ fn validate_password(password: &str) -> Result<(), ValidationError> {
     }
 }
 
+#[derive(Debug, Deserialize, Validate)]
+pub struct ForgotPasswordSchema {
+    #[validate(email(message = "Invalid Email"))]
+    pub email: String,
+}
+
+/// The new password follows the same rules as on registration.
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResetPasswordSchema {
+    pub token: String,
+    #[validate(
+        custom(
+            function = "validate_password",
+            message = "Must Contain At Least and Number. Dont use spaces."
+        ),
+        regex(
+            path = "RE_SPECIAL_CHAR",
+            message = "Must Contain At Least One Special Character"
+        )
+    )]
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateUserSchema {
+    #[validate(length(
+        min = 3,
+        code = "code_str",
+        message = "Name must be greater than 3 chars"
+    ))]
+    pub name: Option<String>,
+    #[validate(email(
+        code = "code_str",
+        message = "Invalid Email, please provide a valid email."
+    ))]
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ChangePasswordSchema {
+    pub current_password: String,
+    #[validate(
+        custom(
+            function = "validate_password",
+            message = "Must Contain At Least and Number. Dont use spaces."
+        ),
+        regex(
+            path = "RE_SPECIAL_CHAR",
+            message = "Must Contain At Least One Special Character"
+        )
+    )]
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteAccountSchema {
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailQuery {
+    pub token: String,
+}
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct LoginUserSchema {
     #[validate(email(message = "Invalid Email"))]
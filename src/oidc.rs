@@ -0,0 +1,275 @@
+//! OpenID Connect login against the provider in `Config::oidc`: authorization code flow
+//! with PKCE, endpoints read from the discovery document, and ID tokens checked against
+//! the keys the provider publishes.
+
+use std::sync::RwLock;
+use std::time::Duration;
+
+use base64::{engine::general_purpose, Engine as _};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
+
+use crate::config_env::OidcConfig;
+use crate::custom_error::{CustomError, CustomHttpError};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+/// Everything needed to send the user to the provider, `state`, `nonce` and
+/// `code_verifier` have to be kept until the callback.
+#[derive(Debug)]
+pub struct AuthorizationRequest {
+    pub url: String,
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub email: Option<String>,
+    #[serde(default, deserialize_with = "lenient_bool")]
+    pub email_verified: bool,
+    pub name: Option<String>,
+    pub nonce: Option<String>,
+}
+
+/// Some providers send `email_verified` as a string.
+fn lenient_bool<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrString {
+        Bool(bool),
+        String(String),
+    }
+
+    Ok(match Option::<BoolOrString>::deserialize(deserializer)? {
+        Some(BoolOrString::Bool(value)) => value,
+        Some(BoolOrString::String(value)) => value.eq_ignore_ascii_case("true"),
+        None => false,
+    })
+}
+
+fn provider_error(err: impl std::fmt::Display) -> CustomError {
+    CustomError::HttpError(CustomHttpError::OidcProviderError(err.to_string()))
+}
+
+fn random_string(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+/// The S256 PKCE challenge of a verifier.
+fn code_challenge(code_verifier: &str) -> String {
+    general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+pub struct OidcClient {
+    config: OidcConfig,
+    http: reqwest::Client,
+    metadata: RwLock<Option<ProviderMetadata>>,
+    keys: RwLock<Option<JwkSet>>,
+}
+
+impl OidcClient {
+    pub fn new(config: OidcConfig) -> Result<Self, CustomError> {
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|err| CustomError::OtherError(err.to_string()))?;
+
+        Ok(OidcClient {
+            config,
+            http,
+            metadata: RwLock::new(None),
+            keys: RwLock::new(None),
+        })
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T, CustomError> {
+        self.http
+            .get(url)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)
+    }
+
+    /// Read from the discovery document once, then kept.
+    async fn metadata(&self) -> Result<ProviderMetadata, CustomError> {
+        if let Some(metadata) = self.metadata.read().unwrap().clone() {
+            return Ok(metadata);
+        }
+
+        let metadata: ProviderMetadata = self
+            .get_json(&format!(
+                "{}/.well-known/openid-configuration",
+                self.config.issuer_url
+            ))
+            .await?;
+
+        if metadata.issuer.trim_end_matches('/') != self.config.issuer_url {
+            return Err(provider_error(format!(
+                "the discovery document is for another issuer: {}",
+                metadata.issuer
+            )));
+        }
+
+        *self.metadata.write().unwrap() = Some(metadata.clone());
+        Ok(metadata)
+    }
+
+    /// The provider's signing keys, fetched again when a token is signed with a key we
+    /// do not know yet, as happens after a key rotation.
+    async fn decoding_key(&self, kid: Option<&str>) -> Result<DecodingKey, CustomError> {
+        let find = |keys: &JwkSet| match kid {
+            Some(kid) => keys.find(kid).cloned(),
+            None => keys.keys.first().cloned(),
+        };
+
+        let cached = self.keys.read().unwrap().as_ref().and_then(find);
+        let jwk = match cached {
+            Some(jwk) => jwk,
+            None => {
+                let metadata = self.metadata().await?;
+                let keys: JwkSet = self.get_json(&metadata.jwks_uri).await?;
+                let jwk = find(&keys);
+                *self.keys.write().unwrap() = Some(keys);
+                jwk.ok_or_else(|| provider_error("the ID token is signed with an unknown key"))?
+            }
+        };
+
+        DecodingKey::from_jwk(&jwk).map_err(provider_error)
+    }
+
+    pub async fn authorization_request(&self) -> Result<AuthorizationRequest, CustomError> {
+        let metadata = self.metadata().await?;
+
+        let state = random_string(32);
+        let nonce = random_string(32);
+        let code_verifier = random_string(64);
+
+        let url = reqwest::Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", self.config.client_id.as_str()),
+                ("redirect_uri", self.config.redirect_url.as_str()),
+                ("scope", self.config.scopes.as_str()),
+                ("state", state.as_str()),
+                ("nonce", nonce.as_str()),
+                ("code_challenge", code_challenge(&code_verifier).as_str()),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(provider_error)?;
+
+        Ok(AuthorizationRequest {
+            url: url.to_string(),
+            state,
+            nonce,
+            code_verifier,
+        })
+    }
+
+    /// Trades the authorization code for the ID token.
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        code_verifier: &str,
+    ) -> Result<String, CustomError> {
+        let metadata = self.metadata().await?;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.config.redirect_url.as_str()),
+            ("client_id", self.config.client_id.as_str()),
+            ("code_verifier", code_verifier),
+        ];
+        if let Some(client_secret) = &self.config.client_secret {
+            form.push(("client_secret", client_secret.as_str()));
+        }
+
+        let response: TokenResponse = self
+            .http
+            .post(&metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)?;
+
+        Ok(response.id_token)
+    }
+
+    /// Checks the signature, issuer, audience, expiry and nonce of an ID token.
+    pub async fn verify_id_token(
+        &self,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, CustomError> {
+        let header = jsonwebtoken::decode_header(id_token).map_err(provider_error)?;
+
+        // Only asymmetric signatures, a shared secret would come from the client itself.
+        if !matches!(
+            header.alg,
+            Algorithm::RS256
+                | Algorithm::RS384
+                | Algorithm::RS512
+                | Algorithm::PS256
+                | Algorithm::PS384
+                | Algorithm::PS512
+                | Algorithm::ES256
+                | Algorithm::ES384
+        ) {
+            return Err(provider_error(format!(
+                "unsupported ID token algorithm {:?}",
+                header.alg
+            )));
+        }
+
+        let metadata = self.metadata().await?;
+        let key = self.decoding_key(header.kid.as_deref()).await?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[metadata.issuer.as_str()]);
+        validation.set_audience(&[self.config.client_id.as_str()]);
+
+        let claims = jsonwebtoken::decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(provider_error)?
+            .claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(provider_error("the ID token nonce does not match"));
+        }
+
+        Ok(claims)
+    }
+}
@@ -0,0 +1,247 @@
+//! Detection of short links that point at other short links on our own hosts.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::config_env::Config;
+use crate::custom_error::{CustomError, CustomHttpError};
+use crate::models::domain::normalize_host;
+
+/// Identifies a short link: its code within the shared namespace or a branded domain.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LinkKey {
+    pub domain_id: Option<Uuid>,
+    pub short_url: String,
+}
+
+/// The hosts that serve our short links.
+pub struct OwnHosts {
+    shared: HashSet<String>,
+    branded: HashMap<String, Uuid>,
+    case_insensitive: bool,
+    case_insensitive_domains: HashSet<Uuid>,
+}
+
+impl OwnHosts {
+    pub async fn load(db: &PgPool, config: &Config) -> Result<Self, CustomError> {
+        let mut shared = HashSet::new();
+        shared.insert(normalize_host(&config.domain));
+        if let Some(host) = url::Url::parse(&config.public_url)
+            .ok()
+            .and_then(|url| url.host_str().map(normalize_host))
+        {
+            shared.insert(host);
+        }
+
+        let domains = sqlx::query!(
+            r#"SELECT id, hostname, case_insensitive FROM domains WHERE verified_at IS NOT NULL"#
+        )
+        .fetch_all(db)
+        .await
+        .map_err(CustomError::DataBaseError)?;
+
+        let case_insensitive_domains = domains
+            .iter()
+            .filter(|domain| domain.case_insensitive)
+            .map(|domain| domain.id)
+            .collect();
+        let branded = domains
+            .into_iter()
+            .map(|domain| (domain.hostname, domain.id))
+            .collect();
+
+        Ok(Self {
+            shared,
+            branded,
+            case_insensitive: config.case_insensitive_short_urls,
+            case_insensitive_domains,
+        })
+    }
+
+    fn is_case_insensitive(&self, domain_id: Option<Uuid>) -> bool {
+        self.case_insensitive
+            || domain_id.is_some_and(|domain_id| self.case_insensitive_domains.contains(&domain_id))
+    }
+
+    /// The form under which two keys reach the same link, lowercased where lookups
+    /// ignore case.
+    fn normalize(&self, key: &LinkKey) -> LinkKey {
+        LinkKey {
+            domain_id: key.domain_id,
+            short_url: if self.is_case_insensitive(key.domain_id) {
+                key.short_url.to_lowercase()
+            } else {
+                key.short_url.clone()
+            },
+        }
+    }
+
+    /// Returns the short link a destination refers to when it is served by us.
+    pub fn resolve(&self, destination: &str) -> Option<LinkKey> {
+        let url = url::Url::parse(destination).ok()?;
+        let host = normalize_host(url.host_str()?);
+        let segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+
+        if let Some(domain_id) = self.branded.get(&host) {
+            return match segments.as_slice() {
+                [short_url] => Some(LinkKey {
+                    domain_id: Some(*domain_id),
+                    short_url: short_url.to_string(),
+                }),
+                _ => None,
+            };
+        }
+
+        if self.shared.contains(&host) {
+            return match segments.as_slice() {
+                ["api", "url", "redirect", short_url] | [short_url] => Some(LinkKey {
+                    domain_id: None,
+                    short_url: short_url.to_string(),
+                }),
+                _ => None,
+            };
+        }
+
+        None
+    }
+}
+
+/// Looks the link up the way a redirect does, an exact match first, then ignoring case
+/// where that is enabled.
+async fn destination_of(
+    db: &PgPool,
+    hosts: &OwnHosts,
+    key: &LinkKey,
+) -> Result<Option<String>, CustomError> {
+    let row = sqlx::query!(
+        r#"
+        SELECT original_url FROM urls
+        WHERE domain_id IS NOT DISTINCT FROM $2
+        AND (short_url = $1 OR ($3 AND lower(short_url) = lower($1)))
+        ORDER BY short_url = $1 DESC
+        LIMIT 1
+        "#,
+        key.short_url,
+        key.domain_id,
+        hosts.is_case_insensitive(key.domain_id)
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    Ok(row.map(|row| row.original_url))
+}
+
+/// Follows `destination` through our own short links and fails when it leads back to
+/// `origin`, runs into an existing loop or exceeds `max_redirect_chain_depth`.
+pub async fn check_redirect_chain(
+    db: &PgPool,
+    config: &Config,
+    origin: &LinkKey,
+    destination: &str,
+) -> Result<(), CustomError> {
+    let hosts = OwnHosts::load(db, config).await?;
+
+    let origin = hosts.normalize(origin);
+    let mut visited = HashSet::new();
+    let mut next = hosts.resolve(destination);
+    let mut depth = 0;
+
+    while let Some(key) = next {
+        let normalized = hosts.normalize(&key);
+        if normalized == origin || !visited.insert(normalized) {
+            return Err(CustomError::HttpError(CustomHttpError::RedirectLoop));
+        }
+
+        depth += 1;
+        if let Some(max_depth) = config.max_redirect_chain_depth {
+            if depth > max_depth {
+                return Err(CustomError::HttpError(
+                    CustomHttpError::RedirectChainTooDeep(max_depth),
+                ));
+            }
+        }
+
+        next = match destination_of(db, &hosts, &key).await? {
+            Some(destination) => hosts.resolve(&destination),
+            None => None,
+        };
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoopMember {
+    pub id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub domain_id: Option<Uuid>,
+    pub short_url: String,
+    pub original_url: String,
+}
+
+/// Lists every redirect loop currently stored, each one as the links forming it.
+pub async fn find_redirect_loops(
+    db: &PgPool,
+    config: &Config,
+) -> Result<Vec<Vec<LoopMember>>, CustomError> {
+    let hosts = OwnHosts::load(db, config).await?;
+
+    let rows = sqlx::query!(r#"SELECT id, user_id, domain_id, short_url, original_url FROM urls"#)
+        .fetch_all(db)
+        .await
+        .map_err(CustomError::DataBaseError)?;
+
+    let mut links = HashMap::new();
+    let mut edges = HashMap::new();
+    for row in rows {
+        let key = hosts.normalize(&LinkKey {
+            domain_id: row.domain_id,
+            short_url: row.short_url.clone(),
+        });
+        if let Some(target) = hosts.resolve(&row.original_url) {
+            edges.insert(key.clone(), hosts.normalize(&target));
+        }
+        links.insert(
+            key,
+            LoopMember {
+                id: row.id,
+                user_id: row.user_id,
+                domain_id: row.domain_id,
+                short_url: row.short_url,
+                original_url: row.original_url,
+            },
+        );
+    }
+
+    // Every link has at most one outgoing edge, so walking from each unvisited link
+    // either ends, joins an earlier walk, or closes a cycle on the current walk.
+    let mut finished: HashSet<LinkKey> = HashSet::new();
+    let mut loops = Vec::new();
+    for start in edges.keys() {
+        let mut path: Vec<LinkKey> = Vec::new();
+        let mut current = Some(start.clone());
+
+        while let Some(key) = current {
+            if finished.contains(&key) || !links.contains_key(&key) {
+                break;
+            }
+            if let Some(position) = path.iter().position(|visited| *visited == key) {
+                loops.push(path[position..].to_vec());
+                break;
+            }
+            path.push(key.clone());
+            current = edges.get(&key).cloned();
+        }
+
+        finished.extend(path);
+    }
+
+    Ok(loops
+        .into_iter()
+        .map(|cycle| cycle.iter().filter_map(|key| links.remove(key)).collect())
+        .collect())
+}
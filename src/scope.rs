@@ -0,0 +1,61 @@
+//! Permissions carried by credentials.
+
+use core::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Scope {
+    #[serde(rename = "links:read")]
+    LinksRead,
+    #[serde(rename = "links:write")]
+    LinksWrite,
+    #[serde(rename = "stats:read")]
+    StatsRead,
+    #[serde(rename = "account:admin")]
+    AccountAdmin,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 4] = [
+        Scope::LinksRead,
+        Scope::LinksWrite,
+        Scope::StatsRead,
+        Scope::AccountAdmin,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::LinksRead => "links:read",
+            Scope::LinksWrite => "links:write",
+            Scope::StatsRead => "stats:read",
+            Scope::AccountAdmin => "account:admin",
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Scope::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == value)
+            .ok_or_else(|| format!("Unknown scope '{}'", value))
+    }
+}
+
+/// Parses the scopes stored as text, dropping the ones this version does not know.
+pub fn parse_scopes(values: &[String]) -> Vec<Scope> {
+    values
+        .iter()
+        .filter_map(|value| value.parse().ok())
+        .collect()
+}
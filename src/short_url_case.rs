@@ -0,0 +1,37 @@
+//! Short codes that must be unique ignoring case. Case-insensitive domains get it from a
+//! partial index on `urls.case_insensitive`, links without a domain from an index that only
+//! exists while `CASE_INSENSITIVE_SHORT_URLS` is on, created or dropped at startup.
+
+use sqlx::PgPool;
+
+use crate::custom_error::CustomError;
+
+/// Creates the unique index of links without a domain when `case_insensitive` is on and
+/// drops it when it is off. Fails while codes collide, the lookup index still serves then.
+pub async fn sync_shared_case_index(
+    db: &PgPool,
+    case_insensitive: bool,
+) -> Result<(), CustomError> {
+    let statement = if case_insensitive {
+        r#"
+        CREATE UNIQUE INDEX IF NOT EXISTS urls_shared_short_url_lower_key
+            ON urls (lower(short_url)) WHERE domain_id IS NULL
+        "#
+    } else {
+        r#"DROP INDEX IF EXISTS urls_shared_short_url_lower_key"#
+    };
+
+    sqlx::query(statement)
+        .execute(db)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                CustomError::OtherError(
+                    "Short codes that only differ by case keep them from being unique, see the short_url_case_collisions view".to_string(),
+                )
+            }
+            err => CustomError::DataBaseError(err),
+        })?;
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+//! Misses are keyed by whatever visitors type, so the table is kept bounded: rows not
+//! seen for `short_url_miss_retention_days` go, then the least recent past
+//! `short_url_miss_max_rows`. Recording them is capped too, a scan would otherwise
+//! turn every request into a write.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use sqlx::PgPool;
+use tracing::{info, warn};
+
+use crate::custom_error::CustomError;
+
+/// Misses recorded per second at most, the rest of a burst is not counted.
+const MAX_RECORDED_MISSES_PER_SECOND: u32 = 20;
+
+lazy_static::lazy_static! {
+    pub static ref MISS_BUDGET: MissBudget = MissBudget::new(MAX_RECORDED_MISSES_PER_SECOND);
+}
+
+/// Lets through at most `max` recordings in every one-second window.
+pub struct MissBudget {
+    max: u32,
+    window: Mutex<(Instant, u32)>,
+}
+
+impl MissBudget {
+    pub fn new(max: u32) -> Self {
+        MissBudget {
+            max,
+            window: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    /// Whether this miss may be recorded.
+    pub fn try_take(&self) -> bool {
+        let mut window = self.window.lock().unwrap_or_else(|err| err.into_inner());
+        if window.0.elapsed() >= Duration::from_secs(1) {
+            *window = (Instant::now(), 0);
+        }
+        if window.1 >= self.max {
+            return false;
+        }
+        window.1 += 1;
+        true
+    }
+}
+
+/// Counts a miss of `short_url` on `host`, or on the shared domain when `host` is not
+/// a verified domain.
+pub async fn record_short_url_miss(
+    db: &PgPool,
+    host: Option<&str>,
+    short_url: &str,
+) -> Result<(), CustomError> {
+    sqlx::query!(
+        r#"
+        INSERT INTO short_url_misses (domain_id, short_url)
+        VALUES ((SELECT id FROM domains WHERE hostname = $1 AND verified_at IS NOT NULL), $2)
+        ON CONFLICT ((COALESCE(domain_id, '00000000-0000-0000-0000-000000000000'::UUID)), short_url)
+        DO UPDATE SET hits = short_url_misses.hits + 1, last_seen_at = now()
+        "#,
+        host,
+        short_url
+    )
+    .execute(db)
+    .await
+    .map_err(CustomError::DataBaseError)?;
+
+    Ok(())
+}
+
+/// Deletes the expired misses and the oldest ones over the cap, returns how many.
+pub async fn prune_short_url_misses(
+    db: &PgPool,
+    retention_days: i32,
+    max_rows: i64,
+) -> Result<u64, CustomError> {
+    let expired = sqlx::query!(
+        r#"DELETE FROM short_url_misses WHERE last_seen_at < now() - make_interval(days => $1)"#,
+        retention_days
+    )
+    .execute(db)
+    .await
+    .map_err(CustomError::DataBaseError)?
+    .rows_affected();
+
+    let over_cap = sqlx::query!(
+        r#"
+        DELETE FROM short_url_misses
+        WHERE id IN (
+            SELECT id FROM short_url_misses
+            ORDER BY last_seen_at DESC, hits DESC
+            OFFSET $1
+        )
+        "#,
+        max_rows
+    )
+    .execute(db)
+    .await
+    .map_err(CustomError::DataBaseError)?
+    .rows_affected();
+
+    Ok(expired + over_cap)
+}
+
+/// Runs `prune_short_url_misses` every `every`.
+pub async fn watch_short_url_misses(
+    db: PgPool,
+    retention_days: i32,
+    max_rows: i64,
+    every: Duration,
+) {
+    let mut interval = actix_web::rt::time::interval(every);
+    loop {
+        interval.tick().await;
+
+        match prune_short_url_misses(&db, retention_days, max_rows).await {
+            Ok(0) => {}
+            Ok(count) => info!("Pruned {} short URL misses", count),
+            Err(err) => warn!("Failed to prune short URL misses: {}", err),
+        }
+    }
+}
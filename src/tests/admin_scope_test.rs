@@ -0,0 +1,40 @@
+#[cfg(test)]
+mod tests {
+    use crate::jwt_auth::JwtMiddleware;
+    use crate::models::user::User;
+    use crate::scope::Scope;
+    use crate::tests::support::test_user;
+
+    fn auth_guard(is_admin: bool, scopes: Vec<Scope>) -> JwtMiddleware {
+        JwtMiddleware {
+            user: User {
+                is_admin,
+                ..test_user()
+            },
+            access_token_uuid: None,
+            api_key_id: Some(uuid::Uuid::new_v4()),
+            scopes,
+        }
+    }
+
+    #[test]
+    fn test_admin_key_without_admin_scope_is_not_admin() {
+        let guard = auth_guard(true, vec![Scope::LinksRead, Scope::LinksWrite]);
+
+        assert!(guard.require(Scope::AccountAdmin).is_err());
+        assert!(!guard.acts_as_admin());
+    }
+
+    #[test]
+    fn test_admin_scope_alone_does_not_make_an_admin() {
+        let guard = auth_guard(false, vec![Scope::AccountAdmin]);
+
+        assert!(guard.require(Scope::AccountAdmin).is_ok());
+        assert!(!guard.acts_as_admin());
+    }
+
+    #[test]
+    fn test_admin_with_admin_scope_is_admin() {
+        assert!(auth_guard(true, vec![Scope::AccountAdmin]).acts_as_admin());
+    }
+}
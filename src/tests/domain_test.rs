@@ -0,0 +1,54 @@
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::api::domain::has_verification_record;
+    use crate::dns_resolver::StaticTxtResolver;
+    use crate::models::domain::Domain;
+
+    fn domain() -> Domain {
+        Domain {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            hostname: "go.example.com".to_string(),
+            verification_token: "s3cr3t".to_string(),
+            case_insensitive: false,
+            verified_at: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_domain_is_verified_by_its_txt_record() {
+        let resolver = StaticTxtResolver::default()
+            .with_record("_url-shortener.go.example.com", "v=spf1 -all")
+            .with_record(
+                "_url-shortener.go.example.com",
+                " url-shortener-verification=s3cr3t ",
+            );
+
+        assert!(has_verification_record(&resolver, &domain()).await.unwrap());
+    }
+
+    #[actix_web::test]
+    async fn test_domain_without_the_token_is_not_verified() {
+        let missing = StaticTxtResolver::default();
+        assert!(!has_verification_record(&missing, &domain()).await.unwrap());
+
+        let other_token = StaticTxtResolver::default().with_record(
+            "_url-shortener.go.example.com",
+            "url-shortener-verification=someone-else",
+        );
+        assert!(!has_verification_record(&other_token, &domain())
+            .await
+            .unwrap());
+
+        // The token has to be under the verification name, not on the host itself.
+        let wrong_name = StaticTxtResolver::default()
+            .with_record("go.example.com", "url-shortener-verification=s3cr3t");
+        assert!(!has_verification_record(&wrong_name, &domain())
+            .await
+            .unwrap());
+    }
+}
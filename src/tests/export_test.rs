@@ -0,0 +1,149 @@
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+
+    use crate::api::export::{parse_columns, ExportWriter, EXPORT_COLUMNS};
+    use crate::custom_error::CustomError;
+    use crate::models::url::{ExportFormat, UrlCategory, UrlRecord};
+
+    fn record(short_url: &str, title: Option<&str>) -> UrlRecord {
+        UrlRecord {
+            user_id: Uuid::nil(),
+            id: Uuid::nil(),
+            domain_id: None,
+            collection_id: None,
+            views: Some(3),
+            original_url: "https://example.com/a?b=1,2".to_string(),
+            short_url: short_url.to_string(),
+            category: UrlCategory::All,
+            slug: short_url.to_string(),
+            title: title.map(str::to_string),
+            notes: None,
+            is_favorite: false,
+            flagged_at: None,
+            flag_reason: None,
+            meta_title: None,
+            meta_description: None,
+            meta_image_url: None,
+            meta_favicon_url: None,
+            metadata_fetched_at: None,
+            health_status_code: None,
+            health_latency_ms: None,
+            health_error: None,
+            health_checked_at: None,
+            health_failures: 0,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    fn columns(columns: &str) -> Vec<String> {
+        parse_columns(Some(columns)).unwrap()
+    }
+
+    /// Everything the writer produces for `records`, header and footer included.
+    fn export(format: ExportFormat, columns: Vec<String>, records: &[UrlRecord]) -> String {
+        let mut writer = ExportWriter::new(format, columns);
+        let mut output = writer.header().unwrap().to_vec();
+        for record in records {
+            output.extend_from_slice(&writer.row(record).unwrap());
+        }
+        output.extend_from_slice(&writer.footer());
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_default_columns() {
+        let all: Vec<String> = EXPORT_COLUMNS.iter().map(|c| c.to_string()).collect();
+
+        assert_eq!(parse_columns(None).unwrap(), all);
+        assert_eq!(parse_columns(Some("  ")).unwrap(), all);
+        // Every default column is a field of the record.
+        let value = serde_json::to_value(record("a", None)).unwrap();
+        for column in EXPORT_COLUMNS {
+            assert!(value.get(column).is_some(), "{} is not exported", column);
+        }
+    }
+
+    #[test]
+    fn test_columns_are_picked_in_order() {
+        assert_eq!(
+            columns(" short_url , views,,original_url "),
+            vec!["short_url", "views", "original_url"]
+        );
+    }
+
+    #[test]
+    fn test_unknown_columns_are_rejected() {
+        for requested in ["short_url,password", "SHORT_URL", "case_insensitive"] {
+            assert!(
+                matches!(
+                    parse_columns(Some(requested)),
+                    Err(CustomError::ValidationError(_))
+                ),
+                "{} was accepted",
+                requested
+            );
+        }
+    }
+
+    #[test]
+    fn test_csv_quotes_fields() {
+        let output = export(
+            ExportFormat::Csv,
+            columns("short_url,original_url,title,views,notes"),
+            &[record("abc", Some(r#"Say "hi", then go"#))],
+        );
+
+        assert_eq!(
+            output,
+            "short_url,original_url,title,views,notes\n\
+             abc,\"https://example.com/a?b=1,2\",\"Say \"\"hi\"\", then go\",3,\n"
+        );
+    }
+
+    #[test]
+    fn test_json_array_commas() {
+        let columns = || columns("short_url");
+
+        assert_eq!(export(ExportFormat::Json, columns(), &[]), "[]");
+
+        let one = export(ExportFormat::Json, columns(), &[record("a", None)]);
+        assert_eq!(one, r#"[{"short_url":"a"}]"#);
+
+        let many = export(
+            ExportFormat::Json,
+            columns(),
+            &[record("a", None), record("b", None), record("c", None)],
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>(&many).unwrap(),
+            json!([{"short_url": "a"}, {"short_url": "b"}, {"short_url": "c"}])
+        );
+    }
+
+    #[test]
+    fn test_ndjson_has_one_object_per_line() {
+        assert_eq!(export(ExportFormat::Ndjson, columns("short_url"), &[]), "");
+
+        let output = export(
+            ExportFormat::Ndjson,
+            columns("short_url,title"),
+            &[record("a", Some("line\nbreak")), record("b", None)],
+        );
+
+        assert!(output.ends_with('\n'));
+        let lines: Vec<Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                json!({"short_url": "a", "title": "line\nbreak"}),
+                json!({"short_url": "b", "title": null}),
+            ]
+        );
+    }
+}
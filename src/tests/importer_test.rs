@@ -0,0 +1,175 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::api::import::import_links;
+    use crate::app_state::AppState;
+    use crate::importers::{bitly::parse_csv, yourls::parse_sql, ImportedLink};
+    use crate::mailer::MemoryMailer;
+    use crate::tests::support::{insert_user, test_db, test_state};
+    use crate::url_safety::BlocklistChecker;
+
+    #[test]
+    fn test_yourls_sql_only_reads_the_links_table() {
+        let dump = r#"
+            -- INSERT INTO yourls_url VALUES ('commented', 'https://example.com/c', '', '', '', 0);
+            INSERT INTO `yourls_log` VALUES (1, '2024-01-01 00:00:00', 'abcde', 'https://referrer.example', 'ua', '127.0.0.1', 'FR');
+            INSERT INTO `yourls_options` VALUES (1, 'note', 'INSERT INTO yourls_url VALUES (''quoted'', ''https://example.com/q'')');
+            INSERT INTO `shorturl` VALUES ('other', 'https://example.com/o', '', '2024-01-01 00:00:00', '', 0);
+            INSERT INTO `yourls_url` (`keyword`, `url`, `title`, `timestamp`, `ip`, `clicks`) VALUES
+                ('first', 'https://example.com/1', 'It''s the first', '2024-01-01 10:00:00', '127.0.0.1', 3),
+                ('second', 'https://example.com/2', 'INSERT INTO yourls_url', '2024-01-02 10:00:00', '127.0.0.1', 7);
+        "#;
+
+        let links = parse_sql(dump).unwrap();
+
+        let codes: Vec<_> = links
+            .iter()
+            .map(|link| link.short_url.clone().unwrap_or_default())
+            .collect();
+        assert_eq!(codes, vec!["first", "second"]);
+        assert_eq!(links[0].original_url, "https://example.com/1");
+        assert_eq!(links[1].views, 7);
+    }
+
+    #[test]
+    fn test_yourls_sql_with_a_custom_table_prefix() {
+        let dump = r#"
+            INSERT INTO `shortener_url` VALUES ('custom', 'https://example.com/c', 'Title', '2024-01-01 10:00:00', '127.0.0.1', 2);
+        "#;
+
+        let links = parse_sql(dump).unwrap();
+
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].short_url.as_deref(), Some("custom"));
+        assert_eq!(links[0].views, 2);
+    }
+
+    #[test]
+    fn test_yourls_sql_without_a_links_table_is_an_error() {
+        let dump =
+            "INSERT INTO `yourls_log` VALUES (1, '2024-01-01 00:00:00', 'abcde', '', '', '', '');";
+
+        assert!(parse_sql(dump).is_err());
+        assert!(parse_sql("").is_err());
+    }
+
+    #[test]
+    fn test_yourls_sql_with_multibyte_text_after_a_keyword() {
+        // `INTO` would end in the middle of the first character.
+        assert!(parse_sql("INSERT 漢字漢字 INTO x; INSERT INTO 漢字").is_err());
+
+        let dump = "INSERT INTO yourls_url VALUES ('kanji', 'https://example.com/漢字', '漢字', '2024-01-01 10:00:00', '', 1);";
+        let links = parse_sql(dump).unwrap();
+        assert_eq!(links[0].original_url, "https://example.com/漢字");
+    }
+
+    #[test]
+    fn test_bitly_csv() {
+        let csv = "\
+id,long_url,custom_bitlinks,clicks,created_at
+bit.ly/3xYzAbC,https://example.com/1,\"https://bit.ly/launch,https://bit.ly/other\",12,2024-01-01T10:00:00+0000
+bit.ly/4aBcDeF,https://example.com/2,,not a number,
+,https://example.com/3,,,
+";
+
+        let links = parse_csv(csv).unwrap();
+
+        assert_eq!(links.len(), 3);
+        assert_eq!(links[0].short_url.as_deref(), Some("launch"));
+        assert_eq!(links[0].views, 12);
+        assert_eq!(
+            links[0].created_at.map(|date| date.to_rfc3339()),
+            Some("2024-01-01T10:00:00+00:00".to_string())
+        );
+        assert_eq!(links[1].short_url.as_deref(), Some("4aBcDeF"));
+        assert_eq!(links[1].views, 0);
+        assert_eq!(links[2].row, 3);
+        assert_eq!(links[2].short_url, None);
+        assert_eq!(links[2].original_url, "https://example.com/3");
+    }
+
+    #[test]
+    fn test_bitly_csv_needs_a_long_url_column() {
+        assert!(parse_csv("link,clicks\nbit.ly/abc,1\n").is_err());
+    }
+
+    fn link(row: usize, original_url: &str, short_url: Option<&str>) -> ImportedLink {
+        ImportedLink {
+            row,
+            original_url: original_url.to_string(),
+            short_url: short_url.map(str::to_string),
+            views: 0,
+            created_at: None,
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_import_reports_conflicts_and_skips() {
+        let blocklist = std::env::temp_dir().join(format!("blocklist-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&blocklist, "phishing.example\n").unwrap();
+
+        let db = test_db().await;
+        let owner = insert_user(&db, "owner@example.com", false).await;
+        let user = insert_user(&db, "user@example.com", false).await;
+        let mut data = AppState {
+            db: db.clone(),
+            url_safety: Arc::new(BlocklistChecker::new(Some(blocklist.clone())).unwrap()),
+            ..test_state(Arc::new(MemoryMailer::default()))
+        };
+        data.secrets.case_insensitive_short_urls = true;
+
+        sqlx::query!(
+            r#"INSERT INTO urls (original_url, short_url, user_id, category, slug) VALUES ('https://example.com', 'taken', $1, 'All', 'taken')"#,
+            owner.id
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let report = import_links(
+            &data,
+            &user,
+            false,
+            vec![
+                link(1, "not a url", Some("broken")),
+                link(2, "https://phishing.example/login", Some("phish")),
+                link(3, "https://example.com/3", Some("keepme")),
+                link(4, "https://example.com/4", Some("taken")),
+                link(5, "https://example.com/5", Some("TAKEN")),
+                link(6, "https://example.com/6", Some("no")),
+                link(7, "https://example.com/7", None),
+            ],
+        )
+        .await
+        .unwrap();
+        std::fs::remove_file(blocklist).unwrap();
+
+        assert_eq!(report.total, 7);
+        assert_eq!(report.imported, 5);
+        assert_eq!(report.preserved, 1);
+
+        let skipped: Vec<usize> = report.skipped.iter().map(|skipped| skipped.row).collect();
+        assert_eq!(skipped, vec![1, 2]);
+
+        let renamed: Vec<&str> = report
+            .renamed
+            .iter()
+            .map(|renamed| renamed.original_code.as_str())
+            .collect();
+        assert_eq!(renamed, vec!["taken", "TAKEN", "no"]);
+        assert!(report
+            .renamed
+            .iter()
+            .all(|renamed| !renamed.short_url.eq_ignore_ascii_case("taken")));
+
+        let imported = sqlx::query_scalar!(
+            r#"SELECT count(*) AS "count!" FROM urls WHERE user_id = $1"#,
+            user.id
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        assert_eq!(imported, 5);
+    }
+}
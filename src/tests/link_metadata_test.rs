@@ -0,0 +1,129 @@
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::header, web, App, HttpResponse, HttpServer};
+
+    use crate::link_metadata::{
+        is_public_ip, FetchedPage, HttpPageFetcher, LinkMetadata, PageFetcher,
+    };
+
+    const PAGE: &str = r#"<!doctype html>
+        <html><head>
+        <title>  Plain   title  </title>
+        <meta name="description" content="Plain description">
+        <meta property="og:title" content="Tom &amp; Jerry">
+        <meta content='The og description' property='og:description'>
+        <meta property="og:image" content="/images/preview.png">
+        <link rel="shortcut icon" href="/static/icon.png">
+        </head><body>Hello</body></html>"#;
+
+    /// Serves `PAGE` on `/page` and redirects `/old` to it, returns the base URL.
+    fn stub_server() -> String {
+        let server = HttpServer::new(|| {
+            App::new()
+                .route(
+                    "/page",
+                    web::get().to(|| async {
+                        HttpResponse::Ok()
+                            .content_type("text/html; charset=utf-8")
+                            .body(PAGE)
+                    }),
+                )
+                .route(
+                    "/old",
+                    web::get().to(|| async {
+                        HttpResponse::MovedPermanently()
+                            .insert_header((header::LOCATION, "/page"))
+                            .finish()
+                    }),
+                )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+
+        let address = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+        format!("http://{}", address)
+    }
+
+    #[actix_web::test]
+    async fn test_metadata_is_read_from_the_page() {
+        let base = stub_server();
+        let fetcher = HttpPageFetcher::new(true).unwrap();
+
+        let page = fetcher.fetch(&format!("{}/old", base)).await.unwrap();
+        assert_eq!(page.url.as_str(), format!("{}/page", base));
+
+        assert_eq!(
+            LinkMetadata::from_page(&page),
+            LinkMetadata {
+                title: Some("Tom & Jerry".to_string()),
+                description: Some("The og description".to_string()),
+                image_url: Some(format!("{}/images/preview.png", base)),
+                favicon_url: Some(format!("{}/static/icon.png", base)),
+            }
+        );
+    }
+
+    #[test]
+    fn test_plain_tags_are_used_without_open_graph() {
+        let page = FetchedPage {
+            url: url::Url::parse("https://example.com/a/b").unwrap(),
+            html: Some(
+                "<title>Just a title</title><meta name=description content=Short>".to_string(),
+            ),
+        };
+
+        assert_eq!(
+            LinkMetadata::from_page(&page),
+            LinkMetadata {
+                title: Some("Just a title".to_string()),
+                description: Some("Short".to_string()),
+                image_url: None,
+                favicon_url: Some("https://example.com/favicon.ico".to_string()),
+            }
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_private_addresses_are_refused() {
+        let base = stub_server();
+        let port = base.rsplit(':').next().unwrap();
+        let fetcher = HttpPageFetcher::new(false).unwrap();
+
+        for url in [
+            format!("{}/page", base),
+            format!("http://localhost:{}/page", port),
+            format!("http://[::ffff:127.0.0.1]:{}/page", port),
+            "http://10.0.0.1/".to_string(),
+            "http://169.254.169.254/latest/meta-data/".to_string(),
+        ] {
+            assert!(fetcher.fetch(&url).await.is_err(), "{} was fetched", url);
+            assert!(fetcher.probe(&url).await.is_err(), "{} was probed", url);
+        }
+
+        assert!(fetcher.fetch("file:///etc/passwd").await.is_err());
+    }
+
+    #[test]
+    fn test_public_ip_ranges() {
+        for ip in ["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "100.64.0.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:192.168.1.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::api::auth::send_verification_email;
+    use crate::api::password_reset::password_reset_email;
+    use crate::mailer::{Mailer, MemoryMailer};
+    use crate::tests::support::{test_config, test_state, test_user};
+    use crate::token::action_token::{verify_action_token, TokenPurpose};
+
+    /// The `token` query parameter of the first link in the body starting with `prefix`.
+    fn token_after(body: &str, prefix: &str) -> String {
+        let start = body.find(prefix).expect("the email has no link") + prefix.len();
+        body[start..].split_whitespace().next().unwrap().to_string()
+    }
+
+    #[actix_web::test]
+    async fn test_verification_email_carries_a_valid_token() {
+        let mailer = Arc::new(MemoryMailer::default());
+        let data = test_state(mailer.clone());
+        let user = test_user();
+
+        send_verification_email(&data, &user).await.unwrap();
+
+        let sent = mailer.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, "ada@example.com");
+        assert!(sent[0].body.contains("expires in 1440 minutes"));
+
+        let token = token_after(&sent[0].body, "https://sho.rt/api/auth/verify?token=");
+        let claims = verify_action_token(
+            &data.secrets.access_token_public_key,
+            &token,
+            TokenPurpose::VerifyEmail,
+        )
+        .unwrap();
+        assert_eq!(claims.sub, user.id);
+        assert_eq!(claims.email, user.email);
+
+        // The same token must not pass for another purpose.
+        assert!(verify_action_token(
+            &data.secrets.access_token_public_key,
+            &token,
+            TokenPurpose::MfaChallenge,
+        )
+        .is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_password_reset_email_links_to_the_client() {
+        let mailer = MemoryMailer::default();
+        let email = password_reset_email(&test_config(), &test_user(), "r3set-t0ken");
+
+        mailer.send(&email).await.unwrap();
+
+        let sent = mailer.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, "ada@example.com");
+        assert_eq!(sent[0].subject, "Reset your password");
+        assert_eq!(
+            token_after(
+                &sent[0].body,
+                "https://app.example.com/reset-password?token="
+            ),
+            "r3set-t0ken"
+        );
+        assert!(sent[0].body.contains("expires in 60 minutes"));
+    }
+}
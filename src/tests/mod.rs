@@ -1,2 +1,18 @@
+mod admin_scope_test;
 mod api_test;
 mod common;
+mod domain_test;
+mod export_test;
+mod importer_test;
+mod link_metadata_test;
+mod mailer_test;
+mod oidc_test;
+mod qr_test;
+mod short_code_test;
+mod short_url_case_test;
+mod short_url_misses_test;
+mod slugify_test;
+#[cfg(test)]
+mod support;
+mod two_factor_test;
+mod url_safety_test;
@@ -0,0 +1,467 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use actix_web::{http::header, web, App, HttpResponse, HttpServer};
+    use base64::{engine::general_purpose, Engine as _};
+    use jsonwebtoken::{Algorithm, EncodingKey, Header};
+    use serde_json::{json, Value};
+    use sha2::{Digest, Sha256};
+
+    use actix_web::cookie::SameSite;
+
+    use crate::api::oidc::{
+        check_state_cookie, find_or_link_user, state_cookie, verify_callback, PendingLogin,
+    };
+    use crate::app_state::AppState;
+    use crate::config_env::OidcConfig;
+    use crate::custom_error::{CustomError, CustomHttpError};
+    use crate::mailer::MemoryMailer;
+    use crate::models::oidc::OidcCallbackQuery;
+    use crate::oidc::{IdTokenClaims, OidcClient};
+    use crate::tests::support::{
+        insert_user, test_db, test_state, TEST_RSA_MODULUS, TEST_RSA_PRIVATE_KEY,
+    };
+
+    const CLIENT_ID: &str = "shortener";
+    const REDIRECT_URL: &str = "https://sho.rt/api/auth/oidc/callback";
+
+    /// Codes handed out by `/authorize`, with the PKCE challenge and nonce they were asked with.
+    type Grants = Arc<Mutex<HashMap<String, (String, String)>>>;
+
+    fn sign(claims: &Value) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some("test-key".to_string());
+        jsonwebtoken::encode(
+            &header,
+            claims,
+            &EncodingKey::from_rsa_pem(TEST_RSA_PRIVATE_KEY.as_bytes()).unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn id_token_claims(issuer: &str, nonce: &str) -> Value {
+        let now = chrono::Utc::now().timestamp();
+        json!({
+            "iss": issuer,
+            "sub": "user-1",
+            "aud": CLIENT_ID,
+            "iat": now,
+            "exp": now + 300,
+            "nonce": nonce,
+            "email": "ada@example.com",
+            "email_verified": "true",
+            "name": "Ada",
+        })
+    }
+
+    /// A provider with discovery, keys, an authorization endpoint that grants at once and
+    /// a token endpoint checking the PKCE verifier, returns its issuer URL.
+    fn mock_provider() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+        let grants = Grants::default();
+
+        let base = issuer.clone();
+        let server = HttpServer::new(move || {
+            let issuer = base.clone();
+            let grants = grants.clone();
+            let authorize_grants = grants.clone();
+
+            App::new()
+                .route(
+                    "/.well-known/openid-configuration",
+                    web::get().to({
+                        let issuer = issuer.clone();
+                        move || {
+                            let issuer = issuer.clone();
+                            async move {
+                                HttpResponse::Ok().json(json!({
+                                    "issuer": issuer,
+                                    "authorization_endpoint": format!("{}/authorize", issuer),
+                                    "token_endpoint": format!("{}/token", issuer),
+                                    "jwks_uri": format!("{}/jwks", issuer),
+                                }))
+                            }
+                        }
+                    }),
+                )
+                .route(
+                    "/jwks",
+                    web::get().to(|| async {
+                        HttpResponse::Ok().json(json!({"keys": [{
+                            "kty": "RSA",
+                            "kid": "test-key",
+                            "use": "sig",
+                            "alg": "RS256",
+                            "n": TEST_RSA_MODULUS,
+                            "e": "AQAB",
+                        }]}))
+                    }),
+                )
+                .route(
+                    "/authorize",
+                    web::get().to(move |query: web::Query<HashMap<String, String>>| {
+                        let grants = authorize_grants.clone();
+                        async move {
+                            let code = format!("code-{}", grants.lock().unwrap().len());
+                            grants.lock().unwrap().insert(
+                                code.clone(),
+                                (query["code_challenge"].clone(), query["nonce"].clone()),
+                            );
+                            let location = reqwest::Url::parse_with_params(
+                                &query["redirect_uri"],
+                                &[("code", code.as_str()), ("state", query["state"].as_str())],
+                            )
+                            .unwrap();
+                            HttpResponse::Found()
+                                .insert_header((header::LOCATION, location.to_string()))
+                                .finish()
+                        }
+                    }),
+                )
+                .route(
+                    "/token",
+                    web::post().to(move |form: web::Form<HashMap<String, String>>| {
+                        let issuer = issuer.clone();
+                        let grants = grants.clone();
+                        async move {
+                            // A code is good for one exchange only.
+                            let grant = grants.lock().unwrap().remove(&form["code"]);
+                            let challenge = general_purpose::URL_SAFE_NO_PAD
+                                .encode(Sha256::digest(form["code_verifier"].as_bytes()));
+
+                            match grant {
+                                Some((expected, nonce))
+                                    if expected == challenge
+                                        && form["client_id"] == CLIENT_ID
+                                        && form["redirect_uri"] == REDIRECT_URL =>
+                                {
+                                    HttpResponse::Ok().json(json!({
+                                        "access_token": "unused",
+                                        "token_type": "Bearer",
+                                        "id_token": sign(&id_token_claims(&issuer, &nonce)),
+                                    }))
+                                }
+                                _ => HttpResponse::BadRequest()
+                                    .json(json!({"error": "invalid_grant"})),
+                            }
+                        }
+                    }),
+                )
+        })
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+
+        actix_web::rt::spawn(server);
+        issuer
+    }
+
+    fn client(issuer: &str) -> OidcClient {
+        OidcClient::new(OidcConfig {
+            issuer_url: issuer.to_string(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: None,
+            redirect_url: REDIRECT_URL.to_string(),
+            scopes: "openid email profile".to_string(),
+        })
+        .unwrap()
+    }
+
+    /// Follows the authorization URL like a browser would, returns the query the
+    /// provider redirects back with.
+    async fn authorize(url: &str) -> HashMap<String, String> {
+        let response = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap()
+            .get(url)
+            .send()
+            .await
+            .unwrap();
+        let location = response.headers()[reqwest::header::LOCATION]
+            .to_str()
+            .unwrap();
+
+        assert!(location.starts_with(REDIRECT_URL));
+        reqwest::Url::parse(location)
+            .unwrap()
+            .query_pairs()
+            .into_owned()
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_login_round_trip() {
+        let issuer = mock_provider();
+        let client = client(&issuer);
+
+        let request = client.authorization_request().await.unwrap();
+        let callback = authorize(&request.url).await;
+        assert_eq!(callback["state"], request.state);
+
+        let id_token = client
+            .exchange_code(&callback["code"], &request.code_verifier)
+            .await
+            .unwrap();
+        let claims = client
+            .verify_id_token(&id_token, &request.nonce)
+            .await
+            .unwrap();
+
+        assert_eq!(claims.iss, issuer);
+        assert_eq!(claims.sub, "user-1");
+        assert_eq!(claims.email.as_deref(), Some("ada@example.com"));
+        assert!(claims.email_verified);
+
+        // Every login gets its own secrets.
+        let other = client.authorization_request().await.unwrap();
+        assert_ne!(other.state, request.state);
+        assert_ne!(other.nonce, request.nonce);
+        assert_ne!(other.code_verifier, request.code_verifier);
+    }
+
+    #[actix_web::test]
+    async fn test_code_needs_the_matching_verifier() {
+        let issuer = mock_provider();
+        let client = client(&issuer);
+
+        let request = client.authorization_request().await.unwrap();
+        let other = client.authorization_request().await.unwrap();
+        let callback = authorize(&request.url).await;
+
+        assert!(client
+            .exchange_code(&callback["code"], &other.code_verifier)
+            .await
+            .is_err());
+
+        let request = client.authorization_request().await.unwrap();
+        let callback = authorize(&request.url).await;
+        client
+            .exchange_code(&callback["code"], &request.code_verifier)
+            .await
+            .unwrap();
+        assert!(client
+            .exchange_code(&callback["code"], &request.code_verifier)
+            .await
+            .is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_id_token_validation() {
+        let issuer = mock_provider();
+        let client = client(&issuer);
+        let claims = id_token_claims(&issuer, "n0nce");
+
+        assert!(client
+            .verify_id_token(&sign(&claims), "n0nce")
+            .await
+            .is_ok());
+        assert!(client
+            .verify_id_token(&sign(&claims), "another-nonce")
+            .await
+            .is_err());
+
+        let tampered = [
+            ("aud", json!("someone-else")),
+            ("iss", json!("https://evil.example.com")),
+            ("exp", json!(chrono::Utc::now().timestamp() - 3600)),
+            ("nonce", Value::Null),
+        ];
+        for (claim, value) in tampered {
+            let mut claims = claims.clone();
+            claims[claim] = value;
+            assert!(
+                client
+                    .verify_id_token(&sign(&claims), "n0nce")
+                    .await
+                    .is_err(),
+                "a token with a bad {} was accepted",
+                claim
+            );
+        }
+
+        // Symmetric signatures and keys the provider never published are refused.
+        let hs256 = jsonwebtoken::encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(CLIENT_ID.as_bytes()),
+        )
+        .unwrap();
+        assert!(client.verify_id_token(&hs256, "n0nce").await.is_err());
+
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some("rotated-away".to_string());
+        let unknown_key = jsonwebtoken::encode(
+            &header,
+            &claims,
+            &EncodingKey::from_rsa_pem(TEST_RSA_PRIVATE_KEY.as_bytes()).unwrap(),
+        )
+        .unwrap();
+        assert!(client.verify_id_token(&unknown_key, "n0nce").await.is_err());
+    }
+
+    #[test]
+    fn test_state_cookie_must_match_the_callback() {
+        let cookie = state_cookie("st4te");
+        assert_eq!(cookie.value(), "st4te");
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(cookie.path(), Some("/api/auth/oidc"));
+
+        assert!(check_state_cookie(Some("st4te"), "st4te").is_ok());
+        for cookie in [None, Some(""), Some("someone-elses")] {
+            assert!(
+                matches!(
+                    check_state_cookie(cookie, "st4te"),
+                    Err(CustomError::HttpError(CustomHttpError::OidcStateInvalid))
+                ),
+                "{:?} was accepted",
+                cookie
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_callback_checks_the_pending_login() {
+        let issuer = mock_provider();
+        let client = client(&issuer);
+
+        let request = client.authorization_request().await.unwrap();
+        let callback = authorize(&request.url).await;
+        let query = OidcCallbackQuery {
+            code: Some(callback["code"].clone()),
+            state: callback["state"].clone(),
+            error: None,
+        };
+
+        // The token carries the nonce of the login it was issued for, not of another one.
+        let other = client.authorization_request().await.unwrap();
+        let mixed_up = PendingLogin {
+            nonce: other.nonce,
+            code_verifier: request.code_verifier.clone(),
+        };
+        assert!(verify_callback(&client, &query, &mixed_up).await.is_err());
+
+        let request = client.authorization_request().await.unwrap();
+        let callback = authorize(&request.url).await;
+        let query = OidcCallbackQuery {
+            code: Some(callback["code"].clone()),
+            state: callback["state"].clone(),
+            error: None,
+        };
+        let pending = PendingLogin {
+            nonce: request.nonce,
+            code_verifier: request.code_verifier,
+        };
+        let claims = verify_callback(&client, &query, &pending).await.unwrap();
+        assert_eq!(claims.sub, "user-1");
+
+        let refused = OidcCallbackQuery {
+            code: None,
+            state: request.state.clone(),
+            error: Some("access_denied".to_string()),
+        };
+        assert!(matches!(
+            verify_callback(&client, &refused, &pending).await,
+            Err(CustomError::HttpError(CustomHttpError::OidcProviderError(error))) if error == "access_denied"
+        ));
+
+        let without_code = OidcCallbackQuery {
+            code: None,
+            state: request.state,
+            error: None,
+        };
+        assert!(matches!(
+            verify_callback(&client, &without_code, &pending).await,
+            Err(CustomError::HttpError(CustomHttpError::OidcStateInvalid))
+        ));
+    }
+
+    fn identity(sub: &str, email: &str, email_verified: bool) -> IdTokenClaims {
+        IdTokenClaims {
+            iss: "https://idp.example.com".to_string(),
+            sub: sub.to_string(),
+            email: Some(email.to_string()),
+            email_verified,
+            name: Some("Ada Lovelace".to_string()),
+            nonce: None,
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_identity_is_linked_by_verified_email() {
+        let db = test_db().await;
+        let data = AppState {
+            db: db.clone(),
+            ..test_state(Arc::new(MemoryMailer::default()))
+        };
+        let user = insert_user(&db, "ada@example.com", false).await;
+        sqlx::query!(
+            "UPDATE users SET email_verified_at = NULL WHERE id = $1",
+            user.id
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let linked = find_or_link_user(&data, &identity("user-1", "Ada@Example.com", true))
+            .await
+            .unwrap();
+        assert_eq!(linked.id, user.id);
+        // The provider vouched for the address.
+        assert!(linked.email_verified_at.is_some());
+
+        // Once linked, the identity is found by its subject whatever email it now has.
+        let again = find_or_link_user(&data, &identity("user-1", "ada@elsewhere.com", false))
+            .await
+            .unwrap();
+        assert_eq!(again.id, user.id);
+
+        let created = find_or_link_user(&data, &identity("user-2", "grace@example.com", true))
+            .await
+            .unwrap();
+        assert_ne!(created.id, user.id);
+        assert_eq!(created.email, "grace@example.com");
+        assert_eq!(created.name, "Ada Lovelace");
+    }
+
+    #[actix_web::test]
+    async fn test_unverified_email_is_refused() {
+        let db = test_db().await;
+        let data = AppState {
+            db: db.clone(),
+            ..test_state(Arc::new(MemoryMailer::default()))
+        };
+        insert_user(&db, "ada@example.com", false).await;
+
+        for claims in [
+            identity("attacker", "ada@example.com", false),
+            identity("attacker", "new@example.com", false),
+            IdTokenClaims {
+                email: None,
+                ..identity("attacker", "", true)
+            },
+        ] {
+            assert!(matches!(
+                find_or_link_user(&data, &claims).await,
+                Err(CustomError::HttpError(
+                    CustomHttpError::OidcEmailNotVerified
+                ))
+            ));
+        }
+
+        let identities = sqlx::query_scalar!(r#"SELECT count(*) AS "count!" FROM user_identities"#)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        let users = sqlx::query_scalar!(r#"SELECT count(*) AS "count!" FROM users"#)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!((identities, users), (0, 1));
+    }
+}
@@ -0,0 +1,49 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::url::QrFormat;
+    use crate::utils::qr::{render_qr, QrOptions};
+
+    #[test]
+    fn test_png_has_the_requested_size() {
+        for size in [64, 100, 256, 333, 1000] {
+            let options = QrOptions {
+                size,
+                ..QrOptions::default()
+            };
+            let png = render_qr("https://example.com/abcde", &options).unwrap();
+
+            let reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+            assert_eq!(reader.info().width, size);
+            assert_eq!(reader.info().height, size);
+        }
+    }
+
+    #[test]
+    fn test_etag_changes_with_every_option() {
+        let base = QrOptions::default();
+        let etag = base.etag("https://example.com/abcde");
+
+        assert_eq!(etag, QrOptions::default().etag("https://example.com/abcde"));
+        assert_ne!(etag, base.etag("https://example.com/other"));
+        for options in [
+            QrOptions {
+                format: QrFormat::Svg,
+                ..QrOptions::default()
+            },
+            QrOptions {
+                size: 512,
+                ..QrOptions::default()
+            },
+            QrOptions {
+                margin: 0,
+                ..QrOptions::default()
+            },
+            QrOptions {
+                foreground: [255, 0, 0],
+                ..QrOptions::default()
+            },
+        ] {
+            assert_ne!(etag, options.etag("https://example.com/abcde"));
+        }
+    }
+}
@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod tests {
+    use crate::models::url::SHORT_URL_REGEX;
+    use crate::utils::short_code::{
+        contains_blocked_word, generate_allowed_code, DEFAULT_BLOCKED_SHORT_CODE_WORDS,
+        DEFAULT_RESERVED_SHORT_CODES, GENERATED_SHORT_CODE_LENGTH,
+    };
+
+    fn blocked_words() -> Vec<String> {
+        DEFAULT_BLOCKED_SHORT_CODE_WORDS
+            .iter()
+            .map(|word| word.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_blocked_words_at_the_edges_are_rejected() {
+        let words = blocked_words();
+        for code in [
+            "shitty",
+            "myshit",
+            "Sh1tHead",
+            "new_p0rn",
+            "my-twat-link",
+            "FUCK",
+        ] {
+            assert!(contains_blocked_word(&words, code), "{} passed", code);
+        }
+    }
+
+    #[test]
+    fn test_blocked_words_inside_a_word_are_allowed() {
+        let words = blocked_words();
+        for code in [
+            "scunthorpe",
+            "Scunthorpe_2024",
+            "peacock",
+            "cocktail",
+            "xshitx",
+        ] {
+            assert!(!contains_blocked_word(&words, code), "{} was blocked", code);
+        }
+    }
+
+    #[test]
+    fn test_default_reserved_codes_are_valid_short_urls() {
+        for code in DEFAULT_RESERVED_SHORT_CODES {
+            assert!(SHORT_URL_REGEX.is_match(code), "{} can never be used", code);
+        }
+    }
+
+    #[test]
+    fn test_generation_gives_up() {
+        assert_eq!(generate_allowed_code(|_| false), None);
+
+        let code = generate_allowed_code(|_| true).unwrap();
+        assert_eq!(code.len(), GENERATED_SHORT_CODE_LENGTH);
+        assert!(SHORT_URL_REGEX.is_match(&code));
+    }
+}
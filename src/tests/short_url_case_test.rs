@@ -0,0 +1,134 @@
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    use crate::short_url_case::sync_shared_case_index;
+    use crate::tests::support::{insert_user, test_db};
+
+    async fn insert_link(
+        db: &PgPool,
+        user_id: Uuid,
+        domain_id: Option<Uuid>,
+        short_url: &str,
+    ) -> Result<Uuid, sqlx::Error> {
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO urls (original_url, short_url, user_id, domain_id, category, slug)
+            VALUES ('https://example.com', $1, $2, $3, 'All', $1)
+            RETURNING id
+            "#,
+            short_url,
+            user_id,
+            domain_id
+        )
+        .fetch_one(db)
+        .await
+    }
+
+    async fn insert_domain(db: &PgPool, user_id: Uuid, hostname: &str) -> Uuid {
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO domains (user_id, hostname, verification_token, verified_at)
+            VALUES ($1, $2, 'token', now())
+            RETURNING id
+            "#,
+            user_id,
+            hostname
+        )
+        .fetch_one(db)
+        .await
+        .unwrap()
+    }
+
+    async fn set_case_insensitive(
+        db: &PgPool,
+        domain_id: Uuid,
+        case_insensitive: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"UPDATE domains SET case_insensitive = $1 WHERE id = $2"#,
+            case_insensitive,
+            domain_id
+        )
+        .execute(db)
+        .await
+        .map(|_| ())
+    }
+
+    fn is_unique_violation<T>(result: Result<T, sqlx::Error>) -> bool {
+        matches!(result, Err(sqlx::Error::Database(err)) if err.is_unique_violation())
+    }
+
+    #[actix_web::test]
+    async fn test_case_insensitive_domains_have_unique_codes() {
+        let db = test_db().await;
+        let user = insert_user(&db, "owner@example.com", false).await;
+        let domain = insert_domain(&db, user.id, "go.example.com").await;
+        let other_domain = insert_domain(&db, user.id, "to.example.com").await;
+
+        insert_link(&db, user.id, Some(domain), "Launch")
+            .await
+            .unwrap();
+        let colliding = insert_link(&db, user.id, Some(domain), "launch")
+            .await
+            .unwrap();
+
+        // Turning the setting on has to wait until the collision is gone.
+        assert!(is_unique_violation(
+            set_case_insensitive(&db, domain, true).await
+        ));
+        sqlx::query!(r#"DELETE FROM urls WHERE id = $1"#, colliding)
+            .execute(&db)
+            .await
+            .unwrap();
+        set_case_insensitive(&db, domain, true).await.unwrap();
+
+        assert!(is_unique_violation(
+            insert_link(&db, user.id, Some(domain), "LAUNCH").await
+        ));
+        insert_link(&db, user.id, None, "LAUNCH").await.unwrap();
+
+        // Moving a link into the domain is checked like creating it there.
+        let moved = insert_link(&db, user.id, Some(other_domain), "launch")
+            .await
+            .unwrap();
+        assert!(is_unique_violation(
+            sqlx::query!(
+                r#"UPDATE urls SET domain_id = $1 WHERE id = $2"#,
+                domain,
+                moved
+            )
+            .execute(&db)
+            .await
+        ));
+
+        set_case_insensitive(&db, domain, false).await.unwrap();
+        insert_link(&db, user.id, Some(domain), "LAUNCH")
+            .await
+            .unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_shared_index_follows_the_global_setting() {
+        let db = test_db().await;
+        let user = insert_user(&db, "owner@example.com", false).await;
+
+        insert_link(&db, user.id, None, "Launch").await.unwrap();
+        let colliding = insert_link(&db, user.id, None, "launch").await.unwrap();
+
+        assert!(sync_shared_case_index(&db, true).await.is_err());
+
+        sqlx::query!(r#"DELETE FROM urls WHERE id = $1"#, colliding)
+            .execute(&db)
+            .await
+            .unwrap();
+        sync_shared_case_index(&db, true).await.unwrap();
+        assert!(is_unique_violation(
+            insert_link(&db, user.id, None, "LAUNCH").await
+        ));
+
+        sync_shared_case_index(&db, false).await.unwrap();
+        insert_link(&db, user.id, None, "LAUNCH").await.unwrap();
+    }
+}
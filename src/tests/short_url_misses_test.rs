@@ -0,0 +1,43 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::short_url_misses::{record_short_url_miss, MissBudget};
+    use crate::tests::support::test_db;
+
+    #[test]
+    fn test_miss_budget_caps_each_second() {
+        let budget = MissBudget::new(3);
+
+        assert!((0..3).all(|_| budget.try_take()));
+        assert!(!budget.try_take());
+
+        std::thread::sleep(Duration::from_millis(1100));
+        assert!(budget.try_take());
+    }
+
+    #[actix_web::test]
+    async fn test_misses_are_counted_per_code() {
+        let db = test_db().await;
+
+        record_short_url_miss(&db, None, "nope").await.unwrap();
+        record_short_url_miss(&db, Some("unknown.example.com"), "nope")
+            .await
+            .unwrap();
+        record_short_url_miss(&db, None, "other").await.unwrap();
+
+        let hits = sqlx::query!(
+            r#"SELECT short_url, hits FROM short_url_misses WHERE domain_id IS NULL ORDER BY short_url"#
+        )
+        .fetch_all(&db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|miss| (miss.short_url, miss.hits))
+        .collect::<Vec<_>>();
+        assert_eq!(
+            hits,
+            vec![("nope".to_string(), 2), ("other".to_string(), 1)]
+        );
+    }
+}
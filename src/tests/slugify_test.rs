@@ -0,0 +1,50 @@
+#[cfg(test)]
+mod tests {
+    use crate::utils::slugify::{slugify, MAX_SLUG_LENGTH};
+
+    #[test]
+    fn test_unicode_is_transliterated() {
+        assert_eq!(
+            slugify("  Crème Brûlée -- Don't Panic! "),
+            "creme-brulee-dont-panic"
+        );
+        assert_eq!(slugify("Straße über Łódź"), "strasse-uber-lodz");
+        assert_eq!(slugify("北京"), "bei-jing");
+    }
+
+    #[test]
+    fn test_separator_runs_collapse() {
+        assert_eq!(slugify("foo_bar"), "foo-bar");
+        assert_eq!(slugify("foo - _ /bar"), "foo-bar");
+        assert_eq!(slugify("a.b,c:d;e+f&g|h"), "a-b-c-d-e-f-g-h");
+        // Other punctuation goes without splitting the word.
+        assert_eq!(slugify("rock'n'roll!?"), "rocknroll");
+    }
+
+    #[test]
+    fn test_no_separator_at_the_edges() {
+        assert_eq!(slugify("--hello--"), "hello");
+        assert_eq!(slugify("_ My Link _"), "my-link");
+    }
+
+    #[test]
+    fn test_nothing_left_gives_an_empty_slug() {
+        assert_eq!(slugify(""), "");
+        assert_eq!(slugify(" -_- "), "");
+        assert_eq!(slugify("?!*"), "");
+    }
+
+    #[test]
+    fn test_long_slugs_are_cut() {
+        let word = "a".repeat(MAX_SLUG_LENGTH + 10);
+        assert_eq!(slugify(&word), "a".repeat(MAX_SLUG_LENGTH));
+
+        // The cut never leaves a separator at the end.
+        let text = format!("{} bcd", "a".repeat(MAX_SLUG_LENGTH - 1));
+        assert_eq!(slugify(&text), "a".repeat(MAX_SLUG_LENGTH - 1));
+
+        let text = format!("{} b", "a".repeat(MAX_SLUG_LENGTH - 2));
+        assert_eq!(slugify(&text).len(), MAX_SLUG_LENGTH);
+        assert!(slugify(&text).ends_with("-b"));
+    }
+}
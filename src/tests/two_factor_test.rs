@@ -0,0 +1,159 @@
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use crate::api::two_factor::check_second_factor;
+    use crate::models::user::User;
+    use crate::tests::support::{insert_user, test_db};
+    use crate::totp::{
+        build_totp, generate_recovery_codes, hash_recovery_code, is_totp_code, verify_code_at,
+    };
+
+    /// The RFC 6238 test secret, `12345678901234567890` in base32.
+    const SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    const ISSUER: &str = "Shortener";
+    /// Start of step 1000.
+    const NOW: u64 = 30_000;
+
+    fn totp() -> totp_rs::TOTP {
+        build_totp(SECRET, ISSUER, "ada@example.com").unwrap()
+    }
+
+    fn code_at_step(step: u64) -> String {
+        totp().generate(step * 30)
+    }
+
+    #[test]
+    fn test_rfc_6238_vector() {
+        // The 8 digit SHA-1 vector at T = 59 is 94287082, its last 6 digits here.
+        assert_eq!(totp().generate(59), "287082");
+        assert_eq!(verify_code_at(&totp(), " 287082 ", None, 59), Some(1));
+    }
+
+    #[test]
+    fn test_one_step_of_skew_is_allowed() {
+        let totp = totp();
+
+        assert_eq!(
+            verify_code_at(&totp, &code_at_step(1000), None, NOW),
+            Some(1000)
+        );
+        assert_eq!(
+            verify_code_at(&totp, &code_at_step(999), None, NOW),
+            Some(999)
+        );
+        assert_eq!(
+            verify_code_at(&totp, &code_at_step(1001), None, NOW + 29),
+            Some(1001)
+        );
+        assert_eq!(verify_code_at(&totp, &code_at_step(998), None, NOW), None);
+        assert_eq!(verify_code_at(&totp, &code_at_step(1002), None, NOW), None);
+        assert_eq!(verify_code_at(&totp, "000000", None, NOW), None);
+    }
+
+    #[test]
+    fn test_used_steps_are_refused() {
+        let totp = totp();
+
+        assert_eq!(
+            verify_code_at(&totp, &code_at_step(1000), Some(1000), NOW),
+            None
+        );
+        // An older code still in the window cannot be replayed after a newer one.
+        assert_eq!(
+            verify_code_at(&totp, &code_at_step(999), Some(1000), NOW),
+            None
+        );
+        assert_eq!(
+            verify_code_at(&totp, &code_at_step(1001), Some(1000), NOW),
+            Some(1001)
+        );
+    }
+
+    #[test]
+    fn test_recovery_codes_are_not_totp_codes() {
+        let codes = generate_recovery_codes();
+
+        assert!(codes.iter().all(|code| !is_totp_code(code)));
+        assert!(is_totp_code(" 123456 "));
+        assert_eq!(
+            hash_recovery_code(" K3J9X-2MF8Q "),
+            hash_recovery_code("k3j9x-2mf8q")
+        );
+    }
+
+    async fn enrolled_user(db: &PgPool, recovery_codes: &[String]) -> User {
+        let user = insert_user(db, "ada@example.com", false).await;
+        let hashes: Vec<String> = recovery_codes
+            .iter()
+            .map(|code| hash_recovery_code(code))
+            .collect();
+        sqlx::query!(
+            r#"UPDATE users SET totp_secret = $1, totp_enabled_at = now() WHERE id = $2"#,
+            SECRET,
+            user.id
+        )
+        .execute(db)
+        .await
+        .unwrap();
+        sqlx::query!(
+            r#"INSERT INTO recovery_codes (user_id, code_hash) SELECT $1, UNNEST($2::VARCHAR[])"#,
+            user.id,
+            &hashes
+        )
+        .execute(db)
+        .await
+        .unwrap();
+
+        User {
+            totp_secret: Some(SECRET.to_string()),
+            ..user
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_recovery_codes_work_once() {
+        let db = test_db().await;
+        let codes = generate_recovery_codes();
+        let user = enrolled_user(&db, &codes).await;
+
+        check_second_factor(&db, ISSUER, &user, &codes[0].to_uppercase())
+            .await
+            .unwrap();
+        assert!(check_second_factor(&db, ISSUER, &user, &codes[0])
+            .await
+            .is_err());
+
+        // The others are still there.
+        check_second_factor(&db, ISSUER, &user, &codes[1])
+            .await
+            .unwrap();
+        assert!(check_second_factor(&db, ISSUER, &user, "aaaaa-bbbbb")
+            .await
+            .is_err());
+
+        let unused = sqlx::query_scalar!(
+            r#"SELECT count(*) AS "count!" FROM recovery_codes WHERE user_id = $1 AND used_at IS NULL"#,
+            user.id
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        assert_eq!(unused as usize, codes.len() - 2);
+    }
+
+    #[actix_web::test]
+    async fn test_totp_code_works_once() {
+        let db = test_db().await;
+        let user = enrolled_user(&db, &[]).await;
+        let code = totp().generate_current().unwrap();
+
+        check_second_factor(&db, ISSUER, &user, &code)
+            .await
+            .unwrap();
+        // `user` still has no used step, the database refuses the replay on its own.
+        assert!(check_second_factor(&db, ISSUER, &user, &code)
+            .await
+            .is_err());
+    }
+}
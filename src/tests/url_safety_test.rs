@@ -0,0 +1,225 @@
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    use crate::tests::support::{insert_user, test_db};
+    use crate::url_safety::{
+        flag_blocked_urls, watch_blocklist, BlocklistChecker, UrlSafetyChecker, UrlVerdict,
+    };
+
+    /// Writes the rules with a modification time of its own, so a rewrite within the
+    /// file system's time granularity is still seen as a change.
+    fn write_blocklist(path: &Path, rules: &str, age_secs: u64) {
+        std::fs::write(path, rules).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(age_secs))
+            .unwrap();
+    }
+
+    fn blocklist_path() -> PathBuf {
+        std::env::temp_dir().join(format!("blocklist-{}", Uuid::new_v4()))
+    }
+
+    async fn is_blocked(checker: &dyn UrlSafetyChecker, url: &str) -> bool {
+        matches!(checker.check(url).await.unwrap(), UrlVerdict::Blocked(_))
+    }
+
+    #[actix_web::test]
+    async fn test_rules_are_parsed() {
+        let path = blocklist_path();
+        write_blocklist(
+            &path,
+            "# phishing campaigns\n\n   \n  Phishing.Example   seen in march\n*.malware.example\n# evil.com\nregex:^https?://[^/]+/wp-login\\.php\n",
+            60,
+        );
+        let checker = BlocklistChecker::new(Some(path.clone())).unwrap();
+
+        for url in [
+            "https://phishing.example/login",
+            "http://PHISHING.example",
+            "https://malware.example/",
+            "https://cdn.malware.example/payload.exe",
+            "https://blog.example.org/wp-login.php",
+        ] {
+            assert!(is_blocked(&checker, url).await, "{} was allowed", url);
+        }
+        for url in [
+            "https://example.com/phishing.example",
+            "https://evil.com",
+            "https://blog.example.org/wp-admin/wp-login.php",
+            "not a url",
+        ] {
+            assert!(!is_blocked(&checker, url).await, "{} was blocked", url);
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_subdomains_match_but_lookalikes_do_not() {
+        let path = blocklist_path();
+        write_blocklist(&path, "evil.com\n", 60);
+        let checker = BlocklistChecker::new(Some(path.clone())).unwrap();
+
+        assert!(is_blocked(&checker, "https://evil.com").await);
+        assert!(is_blocked(&checker, "https://a.b.evil.com/x").await);
+        for url in [
+            "https://notevil.com",
+            "https://evil.com.example.org",
+            "https://evil.co",
+            "https://com",
+        ] {
+            assert!(!is_blocked(&checker, url).await, "{} was blocked", url);
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_unset_path_allows_everything() {
+        let checker = BlocklistChecker::new(None).unwrap();
+
+        assert!(!is_blocked(&checker, "https://phishing.example").await);
+        assert!(!checker.refresh().unwrap());
+    }
+
+    #[actix_web::test]
+    async fn test_refresh_keeps_previous_rules_on_errors() {
+        let path = blocklist_path();
+        write_blocklist(&path, "evil.com\n", 120);
+        let checker = BlocklistChecker::new(Some(path.clone())).unwrap();
+
+        assert!(!checker.refresh().unwrap());
+
+        write_blocklist(&path, "evil.com\nregex:([unclosed\n", 60);
+        assert!(checker.refresh().is_err());
+        assert!(is_blocked(&checker, "https://evil.com").await);
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(checker.refresh().is_err());
+        assert!(is_blocked(&checker, "https://evil.com").await);
+
+        assert!(BlocklistChecker::new(Some(path)).is_err());
+    }
+
+    async fn insert_link(db: &PgPool, user_id: Uuid, original_url: &str) -> Uuid {
+        sqlx::query_scalar!(
+            r#"
+            INSERT INTO urls (original_url, short_url, user_id, category, slug)
+            VALUES ($1, $2, $3, 'All', $2)
+            RETURNING id
+            "#,
+            original_url,
+            Uuid::new_v4().simple().to_string()[..12].to_string(),
+            user_id
+        )
+        .fetch_one(db)
+        .await
+        .unwrap()
+    }
+
+    async fn flags(db: &PgPool, ids: &[Uuid]) -> Vec<(Option<String>, bool)> {
+        let mut flags = Vec::new();
+        for id in ids {
+            let row = sqlx::query!(
+                r#"SELECT flag_reason, flagged_at FROM urls WHERE id = $1"#,
+                id
+            )
+            .fetch_one(db)
+            .await
+            .unwrap();
+            flags.push((row.flag_reason, row.flagged_at.is_some()));
+        }
+        flags
+    }
+
+    #[actix_web::test]
+    async fn test_flag_blocked_urls() {
+        let db = test_db().await;
+        let user = insert_user(&db, "owner@example.com", false).await;
+        let ids = [
+            insert_link(&db, user.id, "https://evil.com/a").await,
+            insert_link(&db, user.id, "https://notevil.com/b").await,
+        ];
+
+        let path = blocklist_path();
+        write_blocklist(&path, "evil.com\n", 120);
+        let checker = BlocklistChecker::new(Some(path.clone())).unwrap();
+
+        assert_eq!(flag_blocked_urls(&db, &checker).await.unwrap(), 1);
+        assert_eq!(
+            flags(&db, &ids).await,
+            vec![
+                (Some("domain evil.com is blocklisted".to_string()), true),
+                (None, false)
+            ]
+        );
+        // Nothing changed, nothing is written.
+        assert_eq!(flag_blocked_urls(&db, &checker).await.unwrap(), 0);
+
+        write_blocklist(&path, "notevil.com\n", 60);
+        assert!(checker.refresh().unwrap());
+        assert_eq!(flag_blocked_urls(&db, &checker).await.unwrap(), 2);
+        assert_eq!(
+            flags(&db, &ids).await,
+            vec![
+                (None, false),
+                (Some("domain notevil.com is blocklisted".to_string()), true)
+            ]
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// Polls until the links carry the expected flags or a few seconds went by.
+    async fn wait_for_flags(db: &PgPool, ids: &[Uuid], expected: &[bool]) {
+        for _ in 0..100 {
+            let flagged: Vec<bool> = flags(db, ids).await.into_iter().map(|f| f.1).collect();
+            if flagged == expected {
+                return;
+            }
+            actix_web::rt::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("links were never flagged as {:?}", expected);
+    }
+
+    #[actix_web::test]
+    async fn test_watch_blocklist_reloads_and_flags() {
+        let db = test_db().await;
+        let user = insert_user(&db, "owner@example.com", false).await;
+        let ids = [
+            insert_link(&db, user.id, "https://evil.com/a").await,
+            insert_link(&db, user.id, "https://phishing.example/b").await,
+        ];
+
+        let path = blocklist_path();
+        write_blocklist(&path, "evil.com\n", 120);
+        let checker: Arc<dyn UrlSafetyChecker> =
+            Arc::new(BlocklistChecker::new(Some(path.clone())).unwrap());
+
+        let watcher = actix_web::rt::spawn(watch_blocklist(
+            db.clone(),
+            checker.clone(),
+            Duration::from_millis(20),
+        ));
+
+        // Existing links are flagged on the first run.
+        wait_for_flags(&db, &ids, &[true, false]).await;
+
+        write_blocklist(&path, "phishing.example\n", 60);
+        wait_for_flags(&db, &ids, &[false, true]).await;
+        assert!(is_blocked(checker.as_ref(), "https://phishing.example").await);
+
+        watcher.abort();
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,72 @@
+//! Short lived tokens sent by email to confirm an action, signed with the access token key.
+
+use base64::{engine::general_purpose, Engine as _};
+use jsonwebtoken::errors::{Error, ErrorKind};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// What a token may be used for, so one kind is never accepted in place of another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenPurpose {
+    VerifyEmail,
+    /// Proves the password was checked, while the second factor is still to come.
+    MfaChallenge,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ActionClaims {
+    pub sub: Uuid,
+    /// The address the token was sent to, it stops working once the user changes it.
+    pub email: String,
+    pub purpose: TokenPurpose,
+    pub exp: i64,
+    pub iat: i64,
+}
+
+pub fn generate_action_token(
+    user_id: Uuid,
+    email: &str,
+    purpose: TokenPurpose,
+    ttl: i64,
+    private_key: &str,
+) -> Result<String, Error> {
+    let bytes_private_key = general_purpose::STANDARD.decode(private_key).unwrap();
+    let decoded_private_key = String::from_utf8(bytes_private_key).unwrap();
+
+    let now = chrono::Utc::now();
+    let claims = ActionClaims {
+        sub: user_id,
+        email: email.to_string(),
+        purpose,
+        exp: (now + chrono::Duration::minutes(ttl)).timestamp(),
+        iat: now.timestamp(),
+    };
+
+    jsonwebtoken::encode(
+        &jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256),
+        &claims,
+        &jsonwebtoken::EncodingKey::from_rsa_pem(decoded_private_key.as_bytes())?,
+    )
+}
+
+pub fn verify_action_token(
+    public_key: &str,
+    token: &str,
+    purpose: TokenPurpose,
+) -> Result<ActionClaims, Error> {
+    let bytes_public_key = general_purpose::STANDARD.decode(public_key).unwrap();
+    let decoded_public_key = String::from_utf8(bytes_public_key).unwrap();
+
+    let claims = jsonwebtoken::decode::<ActionClaims>(
+        token,
+        &jsonwebtoken::DecodingKey::from_rsa_pem(decoded_public_key.as_bytes())?,
+        &jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::RS256),
+    )?
+    .claims;
+
+    if claims.purpose != purpose {
+        return Err(ErrorKind::InvalidToken.into());
+    }
+    Ok(claims)
+}
@@ -0,0 +1,64 @@
+//! Refresh tokens are grouped in families: the one issued at login and every token
+//! rotated from it. A retired refresh token showing up again means it leaked, so the
+//! whole family is revoked.
+
+use redis::aio::Connection;
+use redis::{AsyncCommands, RedisResult};
+use uuid::Uuid;
+
+fn family_key(refresh_token_uuid: Uuid) -> String {
+    format!("refresh_family:{}", refresh_token_uuid)
+}
+
+fn family_tokens_key(family_id: Uuid) -> String {
+    format!("refresh_family_tokens:{}", family_id)
+}
+
+/// The family a refresh token was issued in, kept after the token itself is retired.
+pub async fn family_of(
+    redis: &mut Connection,
+    refresh_token_uuid: Uuid,
+) -> RedisResult<Option<Uuid>> {
+    let family_id: Option<String> = redis.get(family_key(refresh_token_uuid)).await?;
+    Ok(family_id.and_then(|id| Uuid::parse_str(&id).ok()))
+}
+
+/// Adds a freshly issued refresh token and its access token to the family.
+pub async fn add_to_family(
+    redis: &mut Connection,
+    family_id: Uuid,
+    refresh_token_uuid: Uuid,
+    access_token_uuid: Uuid,
+    ttl_seconds: usize,
+) -> RedisResult<()> {
+    let tokens_key = family_tokens_key(family_id);
+
+    redis::pipe()
+        .set_ex(
+            family_key(refresh_token_uuid),
+            family_id.to_string(),
+            ttl_seconds,
+        )
+        .ignore()
+        .sadd(
+            &tokens_key,
+            &[
+                refresh_token_uuid.to_string(),
+                access_token_uuid.to_string(),
+            ],
+        )
+        .ignore()
+        .expire(&tokens_key, ttl_seconds)
+        .ignore()
+        .query_async(redis)
+        .await
+}
+
+/// Invalidates every access and refresh token issued in the family.
+pub async fn revoke_family(redis: &mut Connection, family_id: Uuid) -> RedisResult<()> {
+    let tokens_key = family_tokens_key(family_id);
+    let mut keys: Vec<String> = redis.smembers(&tokens_key).await?;
+    keys.push(tokens_key);
+
+    redis.del(keys).await
+}
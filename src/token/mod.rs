@@ -1 +1,6 @@
+#[allow(clippy::module_inception)]
 pub mod token;
+
+pub mod action_token;
+pub mod family;
+pub mod session;
@@ -0,0 +1,197 @@
+//! One session per login, kept next to its token family and sharing its id, so users
+//! can see where they are logged in and end any of those sessions.
+
+use std::collections::HashMap;
+
+use actix_web::{http::header, HttpRequest};
+use chrono::{DateTime, Utc};
+use redis::aio::Connection;
+use redis::{AsyncCommands, RedisResult};
+use uuid::Uuid;
+
+use crate::config_env::Config;
+use crate::models::session::Session;
+use crate::token::family::revoke_family;
+
+fn session_key(session_id: Uuid) -> String {
+    format!("session:{}", session_id)
+}
+
+fn user_sessions_key(user_id: Uuid) -> String {
+    format!("user_sessions:{}", user_id)
+}
+
+/// Where a request comes from, as recorded on its session.
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+impl ClientInfo {
+    /// The forwarded headers are set by the client unless a proxy we trust overwrote
+    /// them, so the IP is only read from them when the request comes from such a proxy.
+    pub fn from_request(req: &HttpRequest, config: &Config) -> Self {
+        let ip = match req.peer_addr().map(|addr| addr.ip()) {
+            Some(peer) if config.trusted_proxies.contains(&peer) => req
+                .connection_info()
+                .realip_remote_addr()
+                .map(str::to_string),
+            peer => peer.map(|ip| ip.to_string()),
+        };
+
+        ClientInfo {
+            user_agent: req
+                .headers()
+                .get(header::USER_AGENT)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+            ip,
+        }
+    }
+
+    /// A rough label guessed from the user agent, enough to tell sessions apart.
+    pub fn device(&self) -> &'static str {
+        const DEVICES: [(&str, &str); 8] = [
+            ("iphone", "iPhone"),
+            ("ipad", "iPad"),
+            ("android", "Android"),
+            ("windows", "Windows"),
+            ("macintosh", "Mac"),
+            ("linux", "Linux"),
+            ("curl", "Command line"),
+            ("postman", "Postman"),
+        ];
+
+        let user_agent = match &self.user_agent {
+            Some(user_agent) => user_agent.to_lowercase(),
+            None => return "Unknown",
+        };
+
+        DEVICES
+            .iter()
+            .find(|(needle, _)| user_agent.contains(needle))
+            .map_or("Unknown", |(_, device)| device)
+    }
+}
+
+/// Creates the session on login, then moves its last seen time on every refresh.
+pub async fn record_session(
+    redis: &mut Connection,
+    session_id: Uuid,
+    user_id: Uuid,
+    client: &ClientInfo,
+    ttl_seconds: usize,
+) -> RedisResult<()> {
+    let key = session_key(session_id);
+    let index_key = user_sessions_key(user_id);
+    let now = Utc::now().to_rfc3339();
+
+    let mut fields = vec![
+        ("user_id", user_id.to_string()),
+        ("device", client.device().to_string()),
+        ("last_seen_at", now.clone()),
+    ];
+    if let Some(user_agent) = &client.user_agent {
+        fields.push(("user_agent", user_agent.clone()));
+    }
+    if let Some(ip) = &client.ip {
+        fields.push(("ip", ip.clone()));
+    }
+
+    redis::pipe()
+        .hset_multiple(&key, &fields)
+        .ignore()
+        .hset_nx(&key, "created_at", now)
+        .ignore()
+        .expire(&key, ttl_seconds)
+        .ignore()
+        .sadd(&index_key, session_id.to_string())
+        .ignore()
+        .expire(&index_key, ttl_seconds)
+        .ignore()
+        .query_async(redis)
+        .await
+}
+
+fn parse_time(fields: &HashMap<String, String>, name: &str) -> Option<DateTime<Utc>> {
+    fields
+        .get(name)
+        .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// The user's live sessions, most recently seen first.
+pub async fn list_sessions(redis: &mut Connection, user_id: Uuid) -> RedisResult<Vec<Session>> {
+    let index_key = user_sessions_key(user_id);
+    let session_ids: Vec<String> = redis.smembers(&index_key).await?;
+
+    let mut sessions = Vec::with_capacity(session_ids.len());
+    for session_id in session_ids {
+        let id = match Uuid::parse_str(&session_id) {
+            Ok(id) => id,
+            Err(_) => continue,
+        };
+
+        let mut fields: HashMap<String, String> = redis.hgetall(session_key(id)).await?;
+        if fields.is_empty() {
+            // The session expired on its own, drop it from the index.
+            redis.srem::<_, _, ()>(&index_key, &session_id).await?;
+            continue;
+        }
+
+        sessions.push(Session {
+            id,
+            device: fields.remove("device").unwrap_or_default(),
+            user_agent: fields.remove("user_agent"),
+            ip: fields.remove("ip"),
+            created_at: parse_time(&fields, "created_at"),
+            last_seen_at: parse_time(&fields, "last_seen_at"),
+        });
+    }
+
+    sessions.sort_by_key(|session| std::cmp::Reverse(session.last_seen_at));
+    Ok(sessions)
+}
+
+pub async fn owns_session(
+    redis: &mut Connection,
+    user_id: Uuid,
+    session_id: Uuid,
+) -> RedisResult<bool> {
+    redis
+        .sismember(user_sessions_key(user_id), session_id.to_string())
+        .await
+}
+
+/// Revokes every token of the session and forgets it.
+pub async fn end_session(
+    redis: &mut Connection,
+    user_id: Uuid,
+    session_id: Uuid,
+) -> RedisResult<()> {
+    revoke_family(redis, session_id).await?;
+
+    redis::pipe()
+        .del(session_key(session_id))
+        .ignore()
+        .srem(user_sessions_key(user_id), session_id.to_string())
+        .ignore()
+        .query_async(redis)
+        .await
+}
+
+/// Ends every session of the user, returns how many there were.
+pub async fn end_all_sessions(redis: &mut Connection, user_id: Uuid) -> RedisResult<usize> {
+    let session_ids: Vec<String> = redis.smembers(user_sessions_key(user_id)).await?;
+
+    let mut ended = 0;
+    for session_id in session_ids {
+        if let Ok(session_id) = Uuid::parse_str(&session_id) {
+            end_session(redis, user_id, session_id).await?;
+            ended += 1;
+        }
+    }
+
+    redis.del::<_, ()>(user_sessions_key(user_id)).await?;
+    Ok(ended)
+}
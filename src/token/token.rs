@@ -3,12 +3,20 @@ use base64::{engine::general_purpose, Engine as _};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::scope::Scope;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenDetails {
     pub token: Option<String>,
     pub token_uuid: uuid::Uuid,
     pub user_id: uuid::Uuid,
     pub expires_in: Option<i64>,
+    pub scopes: Vec<Scope>,
+}
+
+/// Tokens issued before scopes existed granted full access.
+fn all_scopes() -> Vec<Scope> {
+    Scope::ALL.to_vec()
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -18,12 +26,15 @@ pub struct TokenClaims {
     pub exp: i64,
     pub iat: i64,
     pub nbf: i64,
+    #[serde(default = "all_scopes")]
+    pub scopes: Vec<Scope>,
 }
 
 pub fn generate_jwt_token(
     user_id: uuid::Uuid,
     ttl: i64,
     private_key: String,
+    scopes: Vec<Scope>,
 ) -> Result<TokenDetails, jsonwebtoken::errors::Error> {
     let bytes_private_key = general_purpose::STANDARD.decode(private_key).unwrap();
     let decoded_private_key = String::from_utf8(bytes_private_key).unwrap();
@@ -34,6 +45,7 @@ pub fn generate_jwt_token(
         token_uuid: Uuid::new_v4(),
         expires_in: Some((now + chrono::Duration::minutes(ttl)).timestamp()),
         token: None,
+        scopes,
     };
 
     let claims = TokenClaims {
@@ -42,6 +54,7 @@ pub fn generate_jwt_token(
         exp: token_details.expires_in.unwrap(),
         iat: now.timestamp(),
         nbf: now.timestamp(),
+        scopes: token_details.scopes.clone(),
     };
 
     let header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256);
@@ -76,5 +89,6 @@ pub fn verify_jwt_token(
         token_uuid,
         user_id,
         expires_in: None,
+        scopes: decoded.claims.scopes,
     })
 }
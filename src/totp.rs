@@ -0,0 +1,84 @@
+//! Time based one-time passwords (RFC 6238) for the second login factor, and the
+//! recovery codes standing in for them when the authenticator is lost.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::custom_error::CustomError;
+
+pub const RECOVERY_CODE_COUNT: usize = 10;
+const STEP: u64 = 30;
+const DIGITS: usize = 6;
+
+/// A new base32 encoded secret, as stored and shown to authenticator apps.
+pub fn generate_secret() -> String {
+    Secret::generate_secret().to_encoded().to_string()
+}
+
+pub fn build_totp(secret: &str, issuer: &str, account: &str) -> Result<TOTP, CustomError> {
+    let secret = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|err| CustomError::OtherError(format!("Invalid TOTP secret: {}", err)))?;
+
+    TOTP::new(
+        Algorithm::SHA1,
+        DIGITS,
+        1,
+        STEP,
+        secret,
+        Some(issuer.replace(':', "")),
+        account.replace(':', ""),
+    )
+    .map_err(|err| CustomError::OtherError(format!("Invalid TOTP settings: {}", err)))
+}
+
+/// Whether `code` has the shape of a TOTP code rather than a recovery code.
+pub fn is_totp_code(code: &str) -> bool {
+    let code = code.trim();
+    code.len() == DIGITS && code.chars().all(|c| c.is_ascii_digit())
+}
+
+/// The time step `code` belongs to, one step of clock drift is allowed either way.
+/// Steps up to `last_used_step` are refused so every code works only once.
+pub fn verify_code(totp: &TOTP, code: &str, last_used_step: Option<i64>) -> Option<i64> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    verify_code_at(totp, code, last_used_step, now)
+}
+
+/// `verify_code` as of `now`, in seconds since the epoch.
+pub fn verify_code_at(
+    totp: &TOTP,
+    code: &str,
+    last_used_step: Option<i64>,
+    now: u64,
+) -> Option<i64> {
+    let current = (now / STEP) as i64;
+
+    (current - 1..=current + 1)
+        .filter(|step| last_used_step.is_none_or(|last| *step > last))
+        .find(|step| totp.generate(*step as u64 * STEP) == code.trim())
+}
+
+/// Codes look like `k3j9x-2mf8q`, they are matched without regard to case.
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let code: String = rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(10)
+                .map(|c| char::from(c).to_ascii_lowercase())
+                .collect();
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect()
+}
+
+pub fn hash_recovery_code(code: &str) -> String {
+    format!(
+        "{:x}",
+        Sha256::digest(code.trim().to_lowercase().as_bytes())
+    )
+}
@@ -0,0 +1,241 @@
+//! Destination checks run before a link is created, updated or followed.
+//!
+//! The blocklist file holds one rule per line:
+//!
+//! ```text
+//! # comments and blank lines are ignored
+//! phishing.example     blocks the domain and all of its subdomains
+//! regex:^https?://[^/]+/wp-login\.php
+//! ```
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use regex::Regex;
+use sqlx::PgPool;
+use tracing::{info, warn};
+
+use crate::custom_error::{CustomError, CustomHttpError};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UrlVerdict {
+    Safe,
+    Blocked(String),
+}
+
+pub trait UrlSafetyChecker: Send + Sync {
+    fn check<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<UrlVerdict, CustomError>>;
+
+    /// Picks up new rules, returns true when they changed since the last call.
+    fn refresh(&self) -> Result<bool, CustomError> {
+        Ok(false)
+    }
+}
+
+#[derive(Default)]
+struct Blocklist {
+    domains: HashSet<String>,
+    patterns: Vec<Regex>,
+    modified: Option<SystemTime>,
+}
+
+impl Blocklist {
+    fn parse(content: &str) -> Result<Self, CustomError> {
+        let mut blocklist = Blocklist::default();
+
+        for line in content.lines() {
+            let rule = line.trim();
+            if rule.is_empty() || rule.starts_with('#') {
+                continue;
+            }
+
+            match rule.strip_prefix("regex:") {
+                Some(pattern) => {
+                    blocklist
+                        .patterns
+                        .push(Regex::new(pattern.trim()).map_err(|err| {
+                            CustomError::OtherError(format!("Invalid blocklist pattern: {}", err))
+                        })?)
+                }
+                None => {
+                    let domain = rule.split_whitespace().next().unwrap_or_default();
+                    blocklist
+                        .domains
+                        .insert(domain.trim_start_matches("*.").to_lowercase());
+                }
+            }
+        }
+
+        Ok(blocklist)
+    }
+
+    fn verdict(&self, url: &str) -> UrlVerdict {
+        let host = url::Url::parse(url)
+            .ok()
+            .and_then(|parsed| parsed.host_str().map(|host| host.to_lowercase()));
+
+        if let Some(host) = host {
+            let mut candidate = host.as_str();
+            loop {
+                if self.domains.contains(candidate) {
+                    return UrlVerdict::Blocked(format!("domain {} is blocklisted", candidate));
+                }
+                match candidate.split_once('.') {
+                    Some((_, parent)) => candidate = parent,
+                    None => break,
+                }
+            }
+        }
+
+        match self.patterns.iter().find(|pattern| pattern.is_match(url)) {
+            Some(pattern) => UrlVerdict::Blocked(format!("matches pattern {}", pattern.as_str())),
+            None => UrlVerdict::Safe,
+        }
+    }
+}
+
+/// Checker backed by a local blocklist file, read at startup and reloaded by
+/// `watch_blocklist` when its modification time changes. Checks only look at the
+/// rules in memory, so requests never wait on the file system.
+pub struct BlocklistChecker {
+    path: Option<PathBuf>,
+    blocklist: RwLock<Blocklist>,
+}
+
+impl BlocklistChecker {
+    pub fn new(path: Option<PathBuf>) -> Result<Self, CustomError> {
+        let checker = Self {
+            path,
+            blocklist: RwLock::new(Blocklist::default()),
+        };
+        checker.reload_if_changed()?;
+        Ok(checker)
+    }
+
+    fn reload_if_changed(&self) -> Result<bool, CustomError> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(false),
+        };
+
+        let modified = std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|err| CustomError::OtherError(format!("Cannot read blocklist: {}", err)))?;
+
+        if self.blocklist.read().unwrap().modified == Some(modified) {
+            return Ok(false);
+        }
+
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| CustomError::OtherError(format!("Cannot read blocklist: {}", err)))?;
+        let mut blocklist = Blocklist::parse(&content)?;
+        blocklist.modified = Some(modified);
+
+        info!(
+            "Loaded url blocklist with {} domains and {} patterns",
+            blocklist.domains.len(),
+            blocklist.patterns.len()
+        );
+        *self.blocklist.write().unwrap() = blocklist;
+        Ok(true)
+    }
+}
+
+impl UrlSafetyChecker for BlocklistChecker {
+    fn check<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<UrlVerdict, CustomError>> {
+        Box::pin(async move { Ok(self.blocklist.read().unwrap().verdict(url)) })
+    }
+
+    fn refresh(&self) -> Result<bool, CustomError> {
+        self.reload_if_changed()
+    }
+}
+
+pub async fn ensure_url_is_safe(
+    checker: &dyn UrlSafetyChecker,
+    url: &str,
+) -> Result<(), CustomError> {
+    match checker.check(url).await? {
+        UrlVerdict::Safe => Ok(()),
+        UrlVerdict::Blocked(reason) => {
+            Err(CustomError::HttpError(CustomHttpError::UnsafeUrl(reason)))
+        }
+    }
+}
+
+/// Re-evaluates every stored link, flagging the ones that are now blocked and
+/// clearing the flag on the ones that no longer are.
+pub async fn flag_blocked_urls(
+    db: &PgPool,
+    checker: &dyn UrlSafetyChecker,
+) -> Result<usize, CustomError> {
+    let mut changes = Vec::new();
+    {
+        let mut rows = sqlx::query!(r#"SELECT id, original_url, flag_reason FROM urls"#).fetch(db);
+
+        while let Some(row) = rows.next().await {
+            let row = row.map_err(CustomError::DataBaseError)?;
+            let reason = match checker.check(&row.original_url).await? {
+                UrlVerdict::Safe => None,
+                UrlVerdict::Blocked(reason) => Some(reason),
+            };
+            if reason != row.flag_reason {
+                changes.push((row.id, reason));
+            }
+        }
+    }
+
+    for (id, reason) in &changes {
+        sqlx::query!(
+            r#"
+            UPDATE urls
+            SET flag_reason = $1, flagged_at = CASE WHEN $1::TEXT IS NULL THEN NULL ELSE now() END
+            WHERE id = $2
+            "#,
+            reason.as_deref(),
+            id
+        )
+        .execute(db)
+        .await
+        .map_err(CustomError::DataBaseError)?;
+    }
+
+    Ok(changes.len())
+}
+
+/// Reloads the rules and flags existing links once at startup and again whenever
+/// the rules change. A broken edit to the file keeps the previous rules.
+pub async fn watch_blocklist(db: PgPool, checker: Arc<dyn UrlSafetyChecker>, every: Duration) {
+    let mut interval = actix_web::rt::time::interval(every);
+    let mut first_run = true;
+
+    loop {
+        interval.tick().await;
+
+        // Reading the file blocks, keep it off the runtime threads.
+        let refresher = checker.clone();
+        let changed = match actix_web::web::block(move || refresher.refresh()).await {
+            Ok(Ok(changed)) => changed,
+            Ok(Err(err)) => {
+                warn!("Keeping previous url blocklist: {}", err);
+                false
+            }
+            Err(err) => {
+                warn!("Failed to refresh url blocklist: {}", err);
+                false
+            }
+        };
+
+        if changed || first_run {
+            match flag_blocked_urls(&db, checker.as_ref()).await {
+                Ok(count) => info!("Updated the safety flag of {} links", count),
+                Err(err) => warn!("Failed to flag blocked links: {}", err),
+            }
+            first_run = false;
+        }
+    }
+}
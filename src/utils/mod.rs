@@ -1 +1,3 @@
+pub mod qr;
+pub mod short_code;
 pub mod slugify;
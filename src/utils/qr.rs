@@ -0,0 +1,193 @@
+use qrcode::{Color, EcLevel, QrCode};
+
+use sha2::{Digest, Sha256};
+
+use crate::custom_error::CustomError;
+use crate::models::url::{QrErrorCorrection, QrFormat, QrQuery};
+
+const DEFAULT_SIZE: u32 = 256;
+const DEFAULT_MARGIN: u32 = 4;
+
+#[derive(Debug)]
+pub struct QrOptions {
+    pub format: QrFormat,
+    pub size: u32,
+    pub margin: u32,
+    pub ecc: QrErrorCorrection,
+    pub foreground: [u8; 3],
+    pub background: [u8; 3],
+}
+
+impl Default for QrOptions {
+    fn default() -> Self {
+        Self {
+            format: QrFormat::Png,
+            size: DEFAULT_SIZE,
+            margin: DEFAULT_MARGIN,
+            ecc: QrErrorCorrection::M,
+            foreground: [0, 0, 0],
+            background: [255, 255, 255],
+        }
+    }
+}
+
+impl QrOptions {
+    /// Expects a query that already passed validation.
+    pub fn from_query(query: &QrQuery) -> Self {
+        let defaults = Self::default();
+        Self {
+            format: query.format.unwrap_or(defaults.format),
+            size: query.size.unwrap_or(defaults.size),
+            margin: query.margin.unwrap_or(defaults.margin),
+            ecc: query.ecc.unwrap_or(defaults.ecc),
+            foreground: query
+                .fg
+                .as_deref()
+                .map_or(defaults.foreground, parse_hex_color),
+            background: query
+                .bg
+                .as_deref()
+                .map_or(defaults.background, parse_hex_color),
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self.format {
+            QrFormat::Png => "image/png",
+            QrFormat::Svg => "image/svg+xml",
+        }
+    }
+
+    /// Strong validator for the image, the same link and options always render the same bytes.
+    pub fn etag(&self, data: &str) -> String {
+        let ecc = match self.ecc {
+            QrErrorCorrection::L => "L",
+            QrErrorCorrection::M => "M",
+            QrErrorCorrection::Q => "Q",
+            QrErrorCorrection::H => "H",
+        };
+
+        let mut hasher = Sha256::new();
+        hasher.update(data.as_bytes());
+        hasher.update(
+            format!(
+                "\n{}\n{}\n{}\n{}\n{}\n{}",
+                self.content_type(),
+                self.size,
+                self.margin,
+                ecc,
+                hex_color(self.foreground),
+                hex_color(self.background)
+            )
+            .as_bytes(),
+        );
+        format!("\"{:x}\"", hasher.finalize())
+    }
+}
+
+fn parse_hex_color(value: &str) -> [u8; 3] {
+    let hex = value.trim_start_matches('#');
+    let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).unwrap_or(0);
+    [channel(0), channel(2), channel(4)]
+}
+
+fn hex_color(color: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+pub fn render_qr(data: &str, options: &QrOptions) -> Result<Vec<u8>, CustomError> {
+    let ec_level = match options.ecc {
+        QrErrorCorrection::L => EcLevel::L,
+        QrErrorCorrection::M => EcLevel::M,
+        QrErrorCorrection::Q => EcLevel::Q,
+        QrErrorCorrection::H => EcLevel::H,
+    };
+
+    let code = QrCode::with_error_correction_level(data, ec_level)
+        .map_err(|err| CustomError::OtherError(format!("Could not encode QR code: {}", err)))?;
+    let modules = code.to_colors();
+    let width = code.width() as u32;
+
+    match options.format {
+        QrFormat::Png => render_png(&modules, width, options),
+        QrFormat::Svg => Ok(render_svg(&modules, width, options).into_bytes()),
+    }
+}
+
+/// Modules are drawn with a whole number of pixels each so they stay sharp, the pixels
+/// left over are spread around the code as extra margin to reach exactly `size`. A code
+/// with more modules than `size` pixels is drawn at one pixel per module instead.
+fn render_png(modules: &[Color], width: u32, options: &QrOptions) -> Result<Vec<u8>, CustomError> {
+    let total = width + 2 * options.margin;
+    let scale = (options.size / total).max(1);
+    let pixels = options.size.max(total * scale);
+    let offset = (pixels - total * scale) / 2;
+    let inside = offset..offset + total * scale;
+
+    let mut image = Vec::with_capacity((pixels * pixels * 3) as usize);
+    for y in 0..pixels {
+        for x in 0..pixels {
+            let dark = inside.contains(&x)
+                && inside.contains(&y)
+                && is_dark(
+                    modules,
+                    width,
+                    options.margin,
+                    (x - offset) / scale,
+                    (y - offset) / scale,
+                );
+            image.extend_from_slice(if dark {
+                &options.foreground
+            } else {
+                &options.background
+            });
+        }
+    }
+
+    let mut buffer = Vec::new();
+    let mut encoder = png::Encoder::new(&mut buffer, pixels, pixels);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&image))
+        .map_err(|err| CustomError::OtherError(format!("Could not encode PNG: {}", err)))?;
+
+    Ok(buffer)
+}
+
+fn is_dark(modules: &[Color], width: u32, margin: u32, module_x: u32, module_y: u32) -> bool {
+    module_x >= margin
+        && module_y >= margin
+        && module_x < margin + width
+        && module_y < margin + width
+        && modules[((module_y - margin) * width + module_x - margin) as usize] == Color::Dark
+}
+
+fn render_svg(modules: &[Color], width: u32, options: &QrOptions) -> String {
+    let total = width + 2 * options.margin;
+
+    let mut path = String::new();
+    for (index, module) in modules.iter().enumerate() {
+        if *module == Color::Dark {
+            let x = index as u32 % width + options.margin;
+            let y = index as u32 / width + options.margin;
+            path.push_str(&format!("M{} {}h1v1h-1z", x, y));
+        }
+    }
+
+    format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" "#,
+            r#"viewBox="0 0 {total} {total}" shape-rendering="crispEdges">"#,
+            r#"<rect width="100%" height="100%" fill="{background}"/>"#,
+            r#"<path fill="{foreground}" d="{path}"/></svg>"#
+        ),
+        size = options.size,
+        total = total,
+        background = hex_color(options.background),
+        foreground = hex_color(options.foreground),
+        path = path,
+    )
+}
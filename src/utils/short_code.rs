@@ -0,0 +1,116 @@
+use rand::{distributions::Alphanumeric, Rng};
+
+use crate::config_env::Config;
+use crate::custom_error::{CustomError, CustomHttpError};
+
+pub const GENERATED_SHORT_CODE_LENGTH: usize = 7;
+
+/// Shortest code `SHORT_URL_REGEX` accepts.
+pub const SHORT_CODE_MIN_LENGTH: usize = 5;
+
+/// Random codes tried before giving up, only reached with an absurd blocked word list.
+const MAX_GENERATE_ATTEMPTS: usize = 100;
+
+/// Codes kept free for current and future routes, extended by `RESERVED_SHORT_CODES`.
+/// Shorter routes like `api` need no entry, no code is shorter than `SHORT_CODE_MIN_LENGTH`.
+pub const DEFAULT_RESERVED_SHORT_CODES: [&str; 21] = [
+    "about",
+    "account",
+    "admin",
+    "assets",
+    "billing",
+    "dashboard",
+    "domains",
+    "export",
+    "favicon",
+    "health",
+    "healthchecker",
+    "import",
+    "login",
+    "logout",
+    "redirect",
+    "register",
+    "robots",
+    "settings",
+    "signup",
+    "static",
+    "support",
+];
+
+/// Words rejected at the start or end of a code, extended by `BLOCKED_SHORT_CODE_WORDS`.
+/// Only unambiguous words are listed so ordinary codes like `peacock` still pass.
+pub const DEFAULT_BLOCKED_SHORT_CODE_WORDS: [&str; 14] = [
+    "asshole", "bitch", "bollocks", "cunt", "dildo", "faggot", "fuck", "nigger", "porn", "shit",
+    "slut", "twat", "wanker", "whore",
+];
+
+/// Lowercases the code and undoes common character substitutions, so `Sh1t` reads as `shit`.
+fn normalize_for_filter(code: &str) -> String {
+    code.chars()
+        .filter_map(|c| match c.to_ascii_lowercase() {
+            '0' => Some('o'),
+            '1' | '!' => Some('i'),
+            '3' => Some('e'),
+            '4' | '@' => Some('a'),
+            '5' | '$' => Some('s'),
+            '7' => Some('t'),
+            '_' | '-' | ' ' => None,
+            c => Some(c),
+        })
+        .collect()
+}
+
+pub fn is_reserved(config: &Config, code: &str) -> bool {
+    let code = code.trim().to_lowercase();
+    config.reserved_short_codes.contains(&code)
+}
+
+pub fn is_profane(config: &Config, code: &str) -> bool {
+    contains_blocked_word(&config.blocked_short_code_words, code)
+}
+
+/// Whether a part of the code, split on `_`, `-` and spaces, starts or ends with one of
+/// `words`. A word in the middle of a part is let through, so `scunthorpe` is fine.
+pub(crate) fn contains_blocked_word(words: &[String], code: &str) -> bool {
+    code.split(['_', '-', ' '])
+        .map(normalize_for_filter)
+        .any(|part| {
+            words
+                .iter()
+                .any(|word| part.starts_with(word.as_str()) || part.ends_with(word.as_str()))
+        })
+}
+
+/// Rejects offensive codes for everyone and reserved codes for everyone but admins.
+pub fn validate_short_code(
+    config: &Config,
+    code: &str,
+    allow_reserved: bool,
+) -> Result<(), CustomError> {
+    if is_profane(config, code) {
+        return Err(CustomError::HttpError(CustomHttpError::ShortUrlNotAllowed));
+    }
+    if !allow_reserved && is_reserved(config, code) {
+        return Err(CustomError::HttpError(CustomHttpError::ShortUrlReserved));
+    }
+    Ok(())
+}
+
+pub fn generate_short_code(config: &Config) -> Result<String, CustomError> {
+    generate_allowed_code(|code| validate_short_code(config, code, false).is_ok()).ok_or_else(
+        || CustomError::OtherError("Could not generate an allowed short code".to_string()),
+    )
+}
+
+/// A random code `is_allowed` accepts, `None` after `MAX_GENERATE_ATTEMPTS` refusals.
+pub(crate) fn generate_allowed_code(is_allowed: impl Fn(&str) -> bool) -> Option<String> {
+    (0..MAX_GENERATE_ATTEMPTS)
+        .map(|_| {
+            rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(GENERATED_SHORT_CODE_LENGTH)
+                .map(char::from)
+                .collect::<String>()
+        })
+        .find(|code| is_allowed(code))
+}
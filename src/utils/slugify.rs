@@ -1,3 +1,35 @@
+use deunicode::deunicode;
+
+/// Length of the `slug` column, transliteration can make a slug longer than its text.
+pub const MAX_SLUG_LENGTH: usize = 255;
+
+/// Characters that split words in a slug, anything else that is not alphanumeric is dropped.
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '-' | '_' | '/' | '.' | ',' | ':' | ';' | '+' | '&' | '|')
+}
+
+/// Turns any text into a lowercase ASCII slug, transliterating Unicode on the way,
+/// so `"  Crème Brûlée -- Don't Panic! "` becomes `creme-brulee-dont-panic`. Cut after
+/// `MAX_SLUG_LENGTH` characters, never on a separator.
 pub fn slugify(value: &str) -> String {
-    value.trim().to_lowercase().replace(" ", "-")
+    let mut slug = String::with_capacity(value.len());
+    let mut pending_separator = false;
+
+    for c in deunicode(value).chars() {
+        if c.is_ascii_alphanumeric() {
+            let separator = pending_separator && !slug.is_empty();
+            if slug.len() + usize::from(separator) + 1 > MAX_SLUG_LENGTH {
+                break;
+            }
+            if separator {
+                slug.push('-');
+            }
+            pending_separator = false;
+            slug.push(c.to_ascii_lowercase());
+        } else if is_separator(c) {
+            pending_separator = true;
+        }
+    }
+
+    slug
 }